@@ -0,0 +1,577 @@
+use quote::ToTokens;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use syn::{
+    Attribute, Fields, FnArg, Generics, ImplItem, Item, Lit, Meta, MethodSig, TraitItem, Type,
+    UseTree, Visibility,
+};
+use version::Change;
+
+/// A public item, keyed in an `Api` by its kind and path, such as `fn parser::parse`.
+#[derive(Debug, PartialEq)]
+pub struct PublicItem {
+    /// The parts of the item's declaration that users depend on, without docs or bodies.
+    pub signature: String,
+    /// Adding a variant to an exhaustive enum or a method without a default to a trait breaks
+    /// users, while adding most other items doesn't.
+    pub addition_breaks: bool,
+}
+
+pub type Api = BTreeMap<String, PublicItem>;
+
+/// Collects the public API of the crate rooted at `root`, following `mod` declarations
+/// through `read`, which returns a source file's contents or `None` when it doesn't exist.
+pub fn collect<F>(root: &Path, read: F) -> Result<Api, String>
+where
+    F: Fn(&Path) -> Option<String>,
+{
+    let mut collector = Collector {
+        read,
+        api: Api::new(),
+        impls: Vec::new(),
+    };
+    let directory = root.parent().expect("crate root directory").to_path_buf();
+    collector.file(root, "", &directory)?;
+
+    // Impls only matter for types that are part of the public API.
+    let public_types: BTreeSet<&str> = collector
+        .api
+        .keys()
+        .filter(|key| {
+            ["struct ", "enum ", "union ", "type "]
+                .iter()
+                .any(|kind| key.starts_with(kind))
+        })
+        .map(|key| key.rsplit([' ', ':']).next().unwrap_or(key))
+        .collect();
+    let impls: Vec<(String, PublicItem)> = collector
+        .impls
+        .into_iter()
+        .filter(|(self_name, _, _)| public_types.contains(self_name.as_str()))
+        .map(|(_, key, item)| (key, item))
+        .collect();
+    let mut api = collector.api;
+    api.extend(impls);
+    Ok(api)
+}
+
+/// A compatibility-relevant difference between two versions of an API.
+#[derive(Debug, PartialEq)]
+pub struct ApiChange {
+    pub change: Change,
+    pub description: String,
+}
+
+/// Compares two APIs. Removed items and changed signatures are breaking, and additions are
+/// features unless `PublicItem::addition_breaks` says otherwise.
+pub fn diff(old: &Api, new: &Api) -> Vec<ApiChange> {
+    let mut changes = Vec::new();
+    for (key, old_item) in old {
+        match new.get(key) {
+            None => changes.push(ApiChange {
+                change: Change::Breaking,
+                description: format!("removed `{}`", key),
+            }),
+            Some(new_item) if new_item.signature != old_item.signature => changes.push(ApiChange {
+                change: Change::Breaking,
+                description: format!(
+                    "changed `{}` from `{}` to `{}`",
+                    key, old_item.signature, new_item.signature
+                ),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, new_item) in new {
+        if !old.contains_key(key) {
+            changes.push(ApiChange {
+                change: if new_item.addition_breaks {
+                    Change::Breaking
+                } else {
+                    Change::Feature
+                },
+                description: format!("added `{}`", key),
+            });
+        }
+    }
+    changes
+}
+
+struct Collector<F> {
+    read: F,
+    api: Api,
+    /// Items of inherent and trait impls as the implementing type's name, the key to record
+    /// them under and the item, kept apart until it is known which types are public.
+    impls: Vec<(String, String, PublicItem)>,
+}
+
+impl<F> Collector<F>
+where
+    F: Fn(&Path) -> Option<String>,
+{
+    /// Reads the module at `path`, whose own submodules live in `directory`.
+    fn file(&mut self, path: &Path, module: &str, directory: &Path) -> Result<(), String> {
+        let source = (self.read)(path).ok_or_else(|| format!("{} not found", path.display()))?;
+        let file =
+            syn::parse_file(&source).map_err(|err| format!("{}: {}", path.display(), err))?;
+        self.items(
+            &file.items,
+            module,
+            directory,
+            path.parent().unwrap_or(directory),
+        )
+    }
+
+    fn items(
+        &mut self,
+        items: &[Item],
+        module: &str,
+        directory: &Path,
+        file_directory: &Path,
+    ) -> Result<(), String> {
+        for item in items {
+            self.item(item, module, directory, file_directory)?;
+        }
+        Ok(())
+    }
+
+    fn item(
+        &mut self,
+        item: &Item,
+        module: &str,
+        directory: &Path,
+        file_directory: &Path,
+    ) -> Result<(), String> {
+        match *item {
+            Item::Mod(ref item) if is_public(&item.vis, &item.attrs) => {
+                let name = item.ident.to_string();
+                let path = join(module, &name);
+                let child_directory = directory.join(&name);
+                match (&item.content, path_attribute(&item.attrs)) {
+                    (&Some((_, ref items)), _) => {
+                        self.items(items, &path, &child_directory, file_directory)?
+                    }
+                    (&None, Some(file)) => {
+                        let file = file_directory.join(file);
+                        let parent = file.parent().unwrap_or(directory).to_path_buf();
+                        self.file(&file, &path, &parent)?
+                    }
+                    (&None, None) => {
+                        let file = directory.join(format!("{}.rs", name));
+                        if (self.read)(&file).is_some() {
+                            self.file(&file, &path, &child_directory)?
+                        } else {
+                            let file = child_directory.join("mod.rs");
+                            self.file(&file, &path, &child_directory)?
+                        }
+                    }
+                }
+            }
+            Item::Fn(ref item) if is_public(&item.vis, &item.attrs) => {
+                let sig = MethodSig {
+                    constness: item.constness,
+                    unsafety: item.unsafety,
+                    asyncness: item.asyncness,
+                    abi: item.abi.clone(),
+                    ident: item.ident.clone(),
+                    decl: (*item.decl).clone(),
+                };
+                self.insert("fn", module, &item.ident, signature(anonymous(&sig)), false);
+            }
+            Item::Struct(ref item) if is_public(&item.vis, &item.attrs) => {
+                let name = join(module, &item.ident.to_string());
+                let exhaustive = !is_non_exhaustive(&item.attrs)
+                    && item.fields.iter().all(|field| is_public(&field.vis, &[]));
+                self.insert(
+                    "struct",
+                    module,
+                    &item.ident,
+                    generics(&item.generics),
+                    false,
+                );
+                self.fields(&name, &item.fields, exhaustive);
+            }
+            Item::Union(ref item) if is_public(&item.vis, &item.attrs) => {
+                let name = join(module, &item.ident.to_string());
+                self.insert(
+                    "union",
+                    module,
+                    &item.ident,
+                    generics(&item.generics),
+                    false,
+                );
+                self.fields(&name, &Fields::Named(item.fields.clone()), false);
+            }
+            Item::Enum(ref item) if is_public(&item.vis, &item.attrs) => {
+                let name = join(module, &item.ident.to_string());
+                let exhaustive = !is_non_exhaustive(&item.attrs);
+                self.insert("enum", module, &item.ident, generics(&item.generics), false);
+                for variant in &item.variants {
+                    let mut fields = variant.fields.clone();
+                    for field in fields.iter_mut() {
+                        field.attrs.clear();
+                    }
+                    self.api.insert(
+                        format!("variant {}::{}", name, variant.ident),
+                        self::item(signature(fields), exhaustive),
+                    );
+                }
+            }
+            Item::Trait(ref item) if is_public(&item.vis, &item.attrs) => {
+                let name = join(module, &item.ident.to_string());
+                let supertraits = signature(item.supertraits.clone());
+                self.insert(
+                    "trait",
+                    module,
+                    &item.ident,
+                    format!(
+                        "{}{}: {}",
+                        signature(item.unsafety),
+                        generics(&item.generics),
+                        supertraits
+                    ),
+                    false,
+                );
+                for trait_item in &item.items {
+                    self.trait_item(&name, trait_item);
+                }
+            }
+            Item::Type(ref item) if is_public(&item.vis, &item.attrs) => {
+                let aliased = format!("{} = {}", generics(&item.generics), signature(&item.ty));
+                self.insert("type", module, &item.ident, aliased, false);
+            }
+            Item::Const(ref item) if is_public(&item.vis, &item.attrs) => {
+                self.insert("const", module, &item.ident, signature(&item.ty), false);
+            }
+            Item::Static(ref item) if is_public(&item.vis, &item.attrs) => {
+                let ty = format!("{}{}", signature(item.mutability), signature(&item.ty));
+                self.insert("static", module, &item.ident, ty, false);
+            }
+            Item::Use(ref item) if is_public(&item.vis, &item.attrs) => {
+                self.use_tree(module, "", &item.tree);
+            }
+            Item::Macro(ref item)
+                if item
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path.is_ident("macro_export")) =>
+            {
+                if let Some(ref ident) = item.ident {
+                    self.insert("macro", "", ident, String::new(), false);
+                }
+            }
+            Item::Impl(ref item) if !is_hidden(&item.attrs) => {
+                let self_name = type_name(&item.self_ty);
+                match item.trait_ {
+                    Some((ref negative, ref path, _)) => {
+                        let key = format!(
+                            "impl {}{} for {}",
+                            signature(negative),
+                            signature(path),
+                            signature(&item.self_ty)
+                        );
+                        self.impls
+                            .push((self_name, key, self::item(String::new(), false)));
+                    }
+                    None => {
+                        let owner = join(module, &self_name);
+                        for impl_item in &item.items {
+                            self.impl_item(&self_name, &owner, impl_item);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn insert(
+        &mut self,
+        kind: &str,
+        module: &str,
+        ident: &syn::Ident,
+        signature: String,
+        breaks: bool,
+    ) {
+        self.api.insert(
+            format!("{} {}", kind, join(module, &ident.to_string())),
+            item(signature, breaks),
+        );
+    }
+
+    fn fields(&mut self, owner: &str, fields: &Fields, addition_breaks: bool) {
+        for (index, field) in fields.iter().enumerate() {
+            if is_public(&field.vis, &field.attrs) {
+                let name = field
+                    .ident
+                    .as_ref()
+                    .map_or_else(|| index.to_string(), ToString::to_string);
+                self.api.insert(
+                    format!("field {}::{}", owner, name),
+                    item(signature(&field.ty), addition_breaks),
+                );
+            }
+        }
+    }
+
+    fn trait_item(&mut self, owner: &str, trait_item: &TraitItem) {
+        let (kind, ident, signature, required) = match *trait_item {
+            TraitItem::Method(ref method) if !is_hidden(&method.attrs) => (
+                "fn",
+                &method.sig.ident,
+                self::signature(anonymous(&method.sig)),
+                method.default.is_none(),
+            ),
+            TraitItem::Const(ref constant) if !is_hidden(&constant.attrs) => (
+                "const",
+                &constant.ident,
+                self::signature(&constant.ty),
+                constant.default.is_none(),
+            ),
+            TraitItem::Type(ref ty) if !is_hidden(&ty.attrs) => (
+                "type",
+                &ty.ident,
+                format!(
+                    "{}: {}",
+                    generics(&ty.generics),
+                    self::signature(&ty.bounds)
+                ),
+                ty.default.is_none(),
+            ),
+            _ => return,
+        };
+        self.api.insert(
+            format!("{} {}::{}", kind, owner, ident),
+            item(signature, required),
+        );
+    }
+
+    fn impl_item(&mut self, self_name: &str, owner: &str, impl_item: &ImplItem) {
+        let (kind, ident, signature) = match *impl_item {
+            ImplItem::Method(ref method) if is_public(&method.vis, &method.attrs) => (
+                "fn",
+                &method.sig.ident,
+                self::signature(anonymous(&method.sig)),
+            ),
+            ImplItem::Const(ref constant) if is_public(&constant.vis, &constant.attrs) => {
+                ("const", &constant.ident, self::signature(&constant.ty))
+            }
+            _ => return,
+        };
+        self.impls.push((
+            self_name.to_string(),
+            format!("{} {}::{}", kind, owner, ident),
+            item(signature, false),
+        ));
+    }
+
+    fn use_tree(&mut self, module: &str, prefix: &str, tree: &UseTree) {
+        let (name, target) = match *tree {
+            UseTree::Path(ref path) => {
+                let prefix = format!("{}{}::", prefix, path.ident);
+                return self.use_tree(module, &prefix, &path.tree);
+            }
+            UseTree::Group(ref group) => {
+                for tree in &group.items {
+                    self.use_tree(module, prefix, tree);
+                }
+                return;
+            }
+            UseTree::Name(ref name) => {
+                (name.ident.to_string(), format!("{}{}", prefix, name.ident))
+            }
+            UseTree::Rename(ref rename) => (
+                rename.rename.to_string(),
+                format!("{}{}", prefix, rename.ident),
+            ),
+            UseTree::Glob(_) => (format!("{}*", prefix), format!("{}*", prefix)),
+        };
+        self.api
+            .insert(format!("use {}", join(module, &name)), item(target, false));
+    }
+}
+
+fn item(signature: String, addition_breaks: bool) -> PublicItem {
+    PublicItem {
+        signature,
+        addition_breaks,
+    }
+}
+
+fn join(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", module, name)
+    }
+}
+
+fn signature<T: ToTokens>(tokens: T) -> String {
+    tokens.into_token_stream().to_string()
+}
+
+fn generics(generics: &Generics) -> String {
+    format!(
+        "{}{}",
+        signature(generics),
+        signature(&generics.where_clause)
+    )
+}
+
+/// Drops argument names, which callers don't depend on.
+fn anonymous(sig: &MethodSig) -> MethodSig {
+    let mut sig = sig.clone();
+    for arg in sig.decl.inputs.iter_mut() {
+        if let FnArg::Captured(captured) = arg.clone() {
+            *arg = FnArg::Ignored(captured.ty);
+        }
+    }
+    sig
+}
+
+fn type_name(ty: &Type) -> String {
+    match *ty {
+        Type::Path(ref path) => path
+            .path
+            .segments
+            .iter()
+            .last()
+            .map_or_else(|| signature(ty), |segment| segment.ident.to_string()),
+        _ => signature(ty),
+    }
+}
+
+fn is_public(vis: &Visibility, attrs: &[Attribute]) -> bool {
+    match *vis {
+        Visibility::Public(_) => !is_hidden(attrs),
+        _ => false,
+    }
+}
+
+/// Items marked `#[doc(hidden)]` aren't part of the supported API.
+fn is_hidden(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("doc") && signature(&attr.tts).replace(' ', "") == "(hidden)"
+    })
+}
+
+fn is_non_exhaustive(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("non_exhaustive"))
+}
+
+fn path_attribute(attrs: &[Attribute]) -> Option<PathBuf> {
+    attrs.iter().find_map(|attr| match attr.parse_meta() {
+        Ok(Meta::NameValue(ref meta)) if meta.ident == "path" => match meta.lit {
+            Lit::Str(ref path) => Some(PathBuf::from(path.value())),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(files: &[(&str, &str)]) -> Api {
+        collect(Path::new("src/lib.rs"), |path| {
+            files
+                .iter()
+                .find(|&&(name, _)| Path::new(name) == path)
+                .map(|&(_, source)| source.to_string())
+        })
+        .unwrap()
+    }
+
+    fn descriptions(old: &Api, new: &Api) -> Vec<(Change, String)> {
+        diff(old, new)
+            .into_iter()
+            .map(|change| (change.change, change.description))
+            .collect()
+    }
+
+    #[test]
+    fn collects_public_items_across_modules() {
+        let api = api(&[
+            (
+                "src/lib.rs",
+                "pub mod parser; mod private; pub use parser::parse as run;
+                 /// Docs don't matter.
+                 pub struct Config { pub depth: u32, cache: Vec<u8> }
+                 impl Config { pub fn new(depth: u32) -> Config { unimplemented!() } fn hidden() {} }
+                 impl Default for Config { fn default() -> Self { unimplemented!() } }
+                 struct Private; impl Default for Private { fn default() -> Self { Private } }
+                 impl Private { pub fn exposed_only_to_the_crate() {} }
+                 #[doc(hidden)] pub fn internal() {}",
+            ),
+            ("src/parser/mod.rs", "pub mod token; pub fn parse(input: &str) -> u32 { 0 }"),
+            ("src/parser/token.rs", "pub enum Token { Word(String), End }"),
+            ("src/private.rs", "pub fn unreachable() {}"),
+        ]);
+        let keys: Vec<&str> = api.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "enum parser::token::Token",
+                "field Config::depth",
+                "fn Config::new",
+                "fn parser::parse",
+                "impl Default for Config",
+                "struct Config",
+                "use run",
+                "variant parser::token::Token::End",
+                "variant parser::token::Token::Word",
+            ]
+        );
+        assert_eq!(
+            api["fn parser::parse"].signature,
+            "fn parse ( & str ) -> u32"
+        );
+        assert_eq!(api["use run"].signature, "parser::parse");
+    }
+
+    #[test]
+    fn classifies_changes() {
+        let old = api(&[(
+            "src/lib.rs",
+            "pub fn parse(input: &str) -> u32 { 0 }
+             pub fn removed() {}
+             pub enum Kind { A }
+             #[non_exhaustive] pub enum Open { A }
+             pub trait Visit { fn visit(&self); }",
+        )]);
+        let new = api(&[(
+            "src/lib.rs",
+            "pub fn parse(text: &str) -> u64 { 0 }
+             pub fn added() {}
+             pub enum Kind { A, B }
+             #[non_exhaustive] pub enum Open { A, B }
+             pub trait Visit { fn visit(&self); fn leave(&self) {} fn enter(&self); }",
+        )]);
+        assert_eq!(
+            descriptions(&old, &new),
+            vec![
+                (
+                    Change::Breaking,
+                    String::from("changed `fn parse` from `fn parse ( & str ) -> u32` to `fn parse ( & str ) -> u64`")
+                ),
+                (Change::Breaking, String::from("removed `fn removed`")),
+                (Change::Breaking, String::from("added `fn Visit::enter`")),
+                (Change::Feature, String::from("added `fn Visit::leave`")),
+                (Change::Feature, String::from("added `fn added`")),
+                (Change::Breaking, String::from("added `variant Kind::B`")),
+                (Change::Feature, String::from("added `variant Open::B`")),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_docs_bodies_and_argument_names() {
+        let old = api(&[("src/lib.rs", "/// Old.\npub fn run(a: u8) { one() }")]);
+        let new = api(&[("src/lib.rs", "/// New.\npub fn run(b: u8) { two() }")]);
+        assert_eq!(diff(&old, &new), Vec::new());
+    }
+}
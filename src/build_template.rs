@@ -0,0 +1,171 @@
+use git;
+use std::env;
+
+/// Shorthand for the most common template.
+const GIT_TEMPLATE: &str = "{date}.{short_sha}";
+
+/// Environment variables CI services use for the current run number, in lookup order.
+const CI_RUN_VARIABLES: [&str; 6] = [
+    "GITHUB_RUN_NUMBER",
+    "CI_PIPELINE_IID",
+    "CIRCLE_BUILD_NUM",
+    "TRAVIS_BUILD_NUMBER",
+    "BUILD_NUMBER",
+    "BUILD_BUILDID",
+];
+
+/// The values build metadata templates can refer to.
+#[derive(Debug, Default)]
+pub struct BuildContext {
+    pub date: Option<String>,
+    pub short_sha: Option<String>,
+    pub dirty: bool,
+    pub ci_run: Option<String>,
+}
+
+impl BuildContext {
+    /// Reads the context from git and the environment.
+    pub fn from_environment() -> BuildContext {
+        BuildContext {
+            date: git::head_date(),
+            short_sha: git::short_head(),
+            dirty: git::is_dirty(),
+            ci_run: CI_RUN_VARIABLES
+                .iter()
+                .filter_map(|name| env::var(name).ok())
+                .find(|value| !value.is_empty()),
+        }
+    }
+}
+
+/// Returns whether `template` needs a `BuildContext` to be expanded.
+pub fn is_template(template: &str) -> bool {
+    template == "git" || template.contains('{')
+}
+
+/// Expands `{date}`, `{short_sha}`, `{dirty}` and `{ci_run}` in a build metadata template,
+/// or the `git` shorthand for `{date}.{short_sha}`. Segments that expand to nothing, such as
+/// `{dirty}` on a clean tree, are dropped.
+pub fn expand(template: &str, context: &BuildContext) -> Result<String, String> {
+    let template = if template == "git" {
+        GIT_TEMPLATE
+    } else {
+        template
+    };
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        output.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed `{{` in build metadata `{}`", template))?;
+        let name = &rest[open + 1..open + close];
+        let value = match name {
+            "date" => context.date.as_deref().ok_or("{date} needs a git commit")?,
+            "short_sha" => context
+                .short_sha
+                .as_deref()
+                .ok_or("{short_sha} needs a git commit")?,
+            "dirty" if context.dirty => "dirty",
+            "dirty" => "",
+            "ci_run" => context.ci_run.as_deref().ok_or_else(|| {
+                format!(
+                    "{{ci_run}} needs one of these environment variables: {}",
+                    CI_RUN_VARIABLES.join(", ")
+                )
+            })?,
+            _ => {
+                return Err(format!(
+                    "Unknown placeholder `{{{}}}` in build metadata",
+                    name
+                ))
+            }
+        };
+        output.push_str(value);
+        rest = &rest[open + close + 1..];
+    }
+    output.push_str(rest);
+
+    let output = output
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>()
+        .join(".");
+    if let Some(bad) = output
+        .chars()
+        .find(|&c| !(c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+    {
+        return Err(format!(
+            "Build metadata `{}` expanded from `{}` contains `{}`, only [0-9A-Za-z-] are allowed",
+            output, template, bad
+        ));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> BuildContext {
+        BuildContext {
+            date: Some(String::from("20261018")),
+            short_sha: Some(String::from("3f2a9c1")),
+            dirty: false,
+            ci_run: Some(String::from("42")),
+        }
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        assert_eq!(
+            expand("{date}.{short_sha}", &context()),
+            Ok(String::from("20261018.3f2a9c1"))
+        );
+        assert_eq!(
+            expand("git", &context()),
+            Ok(String::from("20261018.3f2a9c1"))
+        );
+        assert_eq!(
+            expand("ci-{ci_run}.g{short_sha}", &context()),
+            Ok(String::from("ci-42.g3f2a9c1"))
+        );
+    }
+
+    #[test]
+    fn drops_empty_segments() {
+        assert_eq!(
+            expand("{short_sha}.{dirty}", &context()),
+            Ok(String::from("3f2a9c1"))
+        );
+        let dirty = BuildContext {
+            dirty: true,
+            ..context()
+        };
+        assert_eq!(
+            expand("{short_sha}.{dirty}", &dirty),
+            Ok(String::from("3f2a9c1.dirty"))
+        );
+    }
+
+    #[test]
+    fn reports_problems() {
+        assert!(expand("{nope}", &context()).unwrap_err().contains("{nope}"));
+        assert!(expand("{date", &context()).is_err());
+        assert!(expand("{ci_run}", &BuildContext::default())
+            .unwrap_err()
+            .contains("GITHUB_RUN_NUMBER"));
+        let odd = BuildContext {
+            short_sha: Some(String::from("a_b")),
+            ..context()
+        };
+        assert!(expand("{short_sha}", &odd).unwrap_err().contains("`_`"));
+    }
+
+    #[test]
+    fn detects_templates() {
+        assert!(is_template("git"));
+        assert!(is_template("{date}"));
+        assert!(!is_template("1999"));
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{Datelike, NaiveDate};
+use semver::Version;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    FullYear,
+    ShortYear,
+    Month,
+    Week,
+    Day,
+    Micro,
+}
+
+impl Token {
+    fn value(self, today: NaiveDate) -> u64 {
+        match self {
+            Token::FullYear => today.year() as u64,
+            Token::ShortYear => (today.year() - 2000) as u64,
+            Token::Month => u64::from(today.month()),
+            Token::Week => u64::from(today.iso_week().week()),
+            Token::Day => u64::from(today.day()),
+            Token::Micro => 0,
+        }
+    }
+}
+
+/// A calendar versioning scheme such as `YYYY.MM.MICRO`, mapped onto the three semver
+/// components.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalVerFormat {
+    tokens: [Token; 3],
+}
+
+impl Default for CalVerFormat {
+    fn default() -> Self {
+        CalVerFormat {
+            tokens: [Token::FullYear, Token::Month, Token::Micro],
+        }
+    }
+}
+
+impl FromStr for CalVerFormat {
+    type Err = String;
+    fn from_str(input: &str) -> Result<CalVerFormat, Self::Err> {
+        let tokens = input
+            .split('.')
+            .map(|token| match token {
+                "YYYY" => Ok(Token::FullYear),
+                // Semver version numbers can't have leading zeros, so the zero-padded
+                // tokens give the same unpadded numbers as their plain counterparts.
+                "YY" | "0Y" => Ok(Token::ShortYear),
+                "MM" | "0M" => Ok(Token::Month),
+                "WW" | "0W" => Ok(Token::Week),
+                "DD" | "0D" => Ok(Token::Day),
+                "MICRO" => Ok(Token::Micro),
+                _ => Err(format!(
+                    "Unknown calver token `{}`, expected YYYY, YY, 0Y, MM, 0M, WW, 0W, DD, 0D \
+                     or MICRO",
+                    token
+                )),
+            })
+            .collect::<Result<Vec<Token>, String>>()?;
+        if tokens.len() != 3 {
+            return Err(format!(
+                "`{}` has {} parts, a calver format needs exactly three",
+                input,
+                tokens.len()
+            ));
+        }
+        if tokens[0] != Token::FullYear && tokens[0] != Token::ShortYear {
+            return Err(format!("`{}` must start with the year", input));
+        }
+        if tokens[..2].contains(&Token::Micro) {
+            return Err(format!("`{}` can only have MICRO as its last part", input));
+        }
+        Ok(CalVerFormat {
+            tokens: [tokens[0], tokens[1], tokens[2]],
+        })
+    }
+}
+
+impl CalVerFormat {
+    /// Computes the version following `current` on `today`. MICRO counts releases within the
+    /// same date and resets to zero when the date part changes.
+    pub fn next(&self, current: &Version, today: NaiveDate) -> Version {
+        let current_parts = [current.major, current.minor, current.patch];
+        let mut parts = [0; 3];
+        for (part, &token) in parts.iter_mut().zip(self.tokens.iter()) {
+            *part = token.value(today);
+        }
+        if self.tokens[2] == Token::Micro && parts[..2] == current_parts[..2] {
+            parts[2] = current_parts[2] + 1;
+        }
+        Version::new(parts[0], parts[1], parts[2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(format: &str, current: &str, today: (i32, u32, u32)) -> String {
+        let format: CalVerFormat = format.parse().unwrap();
+        let today = NaiveDate::from_ymd_opt(today.0, today.1, today.2).unwrap();
+        format
+            .next(&Version::parse(current).unwrap(), today)
+            .to_string()
+    }
+
+    #[test]
+    fn resets_micro_when_date_changes() {
+        assert_eq!(
+            next("YYYY.MM.MICRO", "2026.9.4", (2026, 10, 3)),
+            "2026.10.0"
+        );
+        assert_eq!(next("YYYY.MM.MICRO", "1.4.2", (2026, 10, 3)), "2026.10.0");
+    }
+
+    #[test]
+    fn increments_micro_within_date() {
+        assert_eq!(
+            next("YYYY.MM.MICRO", "2026.10.0", (2026, 10, 3)),
+            "2026.10.1"
+        );
+        assert_eq!(
+            next("YY.MM.MICRO", "26.10.7-rc.1", (2026, 10, 3)),
+            "26.10.8"
+        );
+    }
+
+    #[test]
+    fn supports_weeks_and_days() {
+        assert_eq!(
+            next("YYYY.WW.MICRO", "2026.1.0", (2026, 10, 3)),
+            "2026.40.0"
+        );
+        assert_eq!(next("YY.MM.DD", "26.10.2", (2026, 10, 3)), "26.10.3");
+    }
+
+    #[test]
+    fn accepts_zero_padded_tokens_unpadded() {
+        assert_eq!("YY.0M.MICRO".parse(), "YY.MM.MICRO".parse::<CalVerFormat>());
+        assert_eq!("0Y.0W.0D".parse(), "YY.WW.DD".parse::<CalVerFormat>());
+        assert_eq!(next("YY.0M.MICRO", "26.9.4", (2026, 3, 1)), "26.3.0");
+        assert_eq!(next("0Y.0M.0D", "26.2.28", (2026, 3, 1)), "26.3.1");
+    }
+
+    #[test]
+    fn rejects_invalid_formats() {
+        assert!("YYYY.MM".parse::<CalVerFormat>().is_err());
+        assert!("MM.YYYY.MICRO".parse::<CalVerFormat>().is_err());
+        assert!("YYYY.MICRO.MM".parse::<CalVerFormat>().is_err());
+        assert!("YYYY.QQ.MICRO".parse::<CalVerFormat>().is_err());
+        assert_eq!("YYYY.MM.MICRO".parse(), Ok(CalVerFormat::default()));
+    }
+}
@@ -0,0 +1,413 @@
+use conventional::ConventionalCommit;
+use git::Commit;
+
+pub const DEFAULT_TEMPLATE: &str = "## {version} - {date}\n\n{sections}";
+
+const SECTIONS: [(&str, &str); 4] = [
+    ("breaking", "Breaking Changes"),
+    ("features", "Features"),
+    ("fixes", "Bug Fixes"),
+    ("performance", "Performance"),
+];
+
+/// Renders a changelog section for `version` from the Conventional Commits in `commits`.
+///
+/// The template may use `{version}`, `{date}`, `{sections}` for every non-empty group, or
+/// `{breaking}`, `{features}`, `{fixes}` and `{performance}` for a single group.
+pub fn render(template: &str, version: &str, date: &str, commits: &[Commit]) -> String {
+    let mut groups: Vec<Vec<String>> = vec![Vec::new(); SECTIONS.len()];
+    for parsed in commits.iter().filter_map(ConventionalCommit::parse) {
+        let group = if parsed.breaking {
+            0
+        } else {
+            match parsed.kind {
+                "feat" => 1,
+                "fix" => 2,
+                "perf" => 3,
+                _ => continue,
+            }
+        };
+        groups[group].push(entry(&parsed));
+    }
+
+    let rendered: Vec<String> = groups
+        .iter()
+        .zip(SECTIONS.iter())
+        .map(|(entries, &(_, title))| {
+            if entries.is_empty() {
+                String::new()
+            } else {
+                format!("### {}\n\n{}\n", title, entries.join("\n"))
+            }
+        })
+        .collect();
+
+    let mut output = template
+        .replace("{version}", version)
+        .replace("{date}", date);
+    let all: Vec<&str> = rendered
+        .iter()
+        .filter(|section| !section.is_empty())
+        .map(String::as_str)
+        .collect();
+    output = output.replace("{sections}", &all.join("\n"));
+    for (&(name, _), section) in SECTIONS.iter().zip(rendered.iter()) {
+        output = output.replace(&format!("{{{}}}", name), section);
+    }
+    output
+}
+
+fn entry(parsed: &ConventionalCommit) -> String {
+    let text = parsed.breaking_note.unwrap_or(parsed.description);
+    match parsed.scope {
+        Some(scope) => format!("- **{}:** {} ({})", scope, text, parsed.short_hash()),
+        None => format!("- {} ({})", text, parsed.short_hash()),
+    }
+}
+
+/// Inserts `section` above the newest release in an existing changelog, keeping any title
+/// or preamble at the top.
+pub fn prepend(changelog: &str, section: &str) -> String {
+    let section = format!("{}\n", section.trim_end());
+    if changelog.trim().is_empty() {
+        return format!("# Changelog\n\n{}", section);
+    }
+    let mut offset = 0;
+    for line in changelog.split_inclusive('\n') {
+        if line.starts_with("## ") {
+            return format!(
+                "{}{}\n{}",
+                &changelog[..offset],
+                section,
+                &changelog[offset..]
+            );
+        }
+        offset += line.len();
+    }
+    format!("{}\n\n{}", changelog.trim_end(), section)
+}
+
+/// Adds the generated `section` for `version` to a changelog. A Keep a Changelog file is
+/// rolled over first and the generated entries go below the hand-written ones in the
+/// released section, so nothing is left behind under Unreleased. Other changelogs get the
+/// section prepended.
+pub fn add_release(changelog: &str, section: &str, version: &str, date: &str) -> String {
+    let rolled = match rollover(changelog, version, date) {
+        Some(rolled) => rolled,
+        None => return prepend(changelog, section),
+    };
+    // The released section has its own heading, so drop the generated one.
+    let entries = match section.split_once('\n') {
+        Some((first, rest)) if first.contains(version) => rest,
+        _ => section,
+    }
+    .trim();
+
+    let lines: Vec<&str> = rolled.lines().collect();
+    let heading = format!("## [{}]", version);
+    let start = lines
+        .iter()
+        .position(|line| line.starts_with(&heading))
+        .expect("rolled over release heading");
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(|line| line.starts_with("## ") || is_link_definition(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    while end > start + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    let mut output = lines[..end].to_vec();
+    if !entries.is_empty() {
+        output.push("");
+        output.extend(entries.lines());
+    }
+    if lines.get(end).is_some_and(|line| !line.trim().is_empty()) {
+        output.push("");
+    }
+    output.extend(&lines[end..]);
+    let mut output = output.join("\n");
+    if rolled.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+fn is_unreleased_heading(line: &str) -> bool {
+    line.trim_end().eq_ignore_ascii_case("## [unreleased]")
+}
+
+fn is_link_definition(line: &str) -> bool {
+    line.starts_with('[') && line.contains("]: ")
+}
+
+/// Finds the `## [Unreleased]` heading and the line that ends its section.
+fn unreleased_section(lines: &[&str]) -> Option<(usize, usize)> {
+    let start = lines.iter().position(|line| is_unreleased_heading(line))?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.starts_with("## ") || is_link_definition(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    Some((start, end))
+}
+
+/// Returns whether a Keep a Changelog `## [Unreleased]` section exists but has no entries.
+pub fn unreleased_is_empty(changelog: &str) -> bool {
+    let lines: Vec<&str> = changelog.lines().collect();
+    match unreleased_section(&lines) {
+        Some((start, end)) => lines[start + 1..end]
+            .iter()
+            .all(|line| line.trim().is_empty() || line.starts_with("### ")),
+        None => false,
+    }
+}
+
+/// Releases the `## [Unreleased]` section of a Keep a Changelog file as `version`, leaving a
+/// fresh Unreleased section above it and updating the compare links at the bottom. Returns
+/// `None` when there is no Unreleased section.
+pub fn rollover(changelog: &str, version: &str, date: &str) -> Option<String> {
+    let lines: Vec<&str> = changelog.lines().collect();
+    let (start, _) = unreleased_section(&lines)?;
+    let mut output = Vec::with_capacity(lines.len() + 3);
+    for (index, line) in lines.iter().enumerate() {
+        let link = match line.get(..14) {
+            Some(label) if label.eq_ignore_ascii_case("[unreleased]: ") => {
+                compare_links(&line[14..], version)
+            }
+            _ => None,
+        };
+        if index == start {
+            output.push(line.to_string());
+            output.push(String::new());
+            output.push(format!("## [{}] - {}", version, date));
+        } else if let Some((unreleased, released)) = link {
+            output.push(format!("{}{}", &line[..14], unreleased));
+            output.push(format!("[{}]: {}", version, released));
+        } else {
+            output.push(line.to_string());
+        }
+    }
+    let mut output = output.join("\n");
+    if changelog.ends_with('\n') {
+        output.push('\n');
+    }
+    Some(output)
+}
+
+/// The text of the `## ` section released as `version`, without its heading, for tag
+/// messages and release pages. Headings like `## 1.2.0`, `## [1.2.0] - 2026-10-18` and
+/// `## v1.2.0` all match. Returns `None` when there is no such section or it is empty.
+pub fn release_notes(changelog: &str, version: &str) -> Option<String> {
+    let lines: Vec<&str> = changelog.lines().collect();
+    let start = lines.iter().position(|line| {
+        line.strip_prefix("## ")
+            .and_then(|heading| heading.split_whitespace().next())
+            .map(|label| label.trim_matches(|c| c == '[' || c == ']'))
+            .map(|label| label.strip_prefix('v').unwrap_or(label))
+            == Some(version)
+    })?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.starts_with("## ") || is_link_definition(line))
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    let notes = lines[start + 1..end].join("\n").trim().to_string();
+    if notes.is_empty() {
+        None
+    } else {
+        Some(notes)
+    }
+}
+
+/// Rewrites a `.../compare/<previous>...HEAD` link into the new Unreleased link and the link
+/// for the release, keeping the `v` prefix convention of the previous tag.
+fn compare_links(url: &str, version: &str) -> Option<(String, String)> {
+    let base = &url[..url.rfind("/compare/")? + "/compare/".len()];
+    let previous = url[base.len()..].strip_suffix("...HEAD")?;
+    let tag = if previous.starts_with('v') {
+        format!("v{}", version)
+    } else {
+        version.to_string()
+    };
+    Some((
+        format!("{}{}...HEAD", base, tag),
+        format!("{}{}...{}", base, previous, tag),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conventional::commit;
+
+    fn commits() -> Vec<Commit> {
+        vec![
+            commit("feat(cli): add --changelog", ""),
+            commit("fix: handle empty tags", ""),
+            commit("chore: update deps", ""),
+            commit("perf: cache metadata", ""),
+            commit(
+                "feat!: drop workspaces",
+                "BREAKING CHANGE: workspaces are rejected",
+            ),
+        ]
+    }
+
+    #[test]
+    fn renders_default_template() {
+        let output = render(DEFAULT_TEMPLATE, "1.2.0", "2026-10-18", &commits());
+        assert_eq!(
+            output,
+            "## 1.2.0 - 2026-10-18
+
+### Breaking Changes
+
+- workspaces are rejected (3f2a9c1)
+
+### Features
+
+- **cli:** add --changelog (3f2a9c1)
+
+### Bug Fixes
+
+- handle empty tags (3f2a9c1)
+
+### Performance
+
+- cache metadata (3f2a9c1)
+"
+        );
+    }
+
+    #[test]
+    fn renders_single_groups() {
+        let output = render("# v{version}\n{fixes}", "1.2.0", "", &commits());
+        assert_eq!(
+            output,
+            "# v1.2.0\n### Bug Fixes\n\n- handle empty tags (3f2a9c1)\n"
+        );
+        let output = render("{breaking}{version}", "1.2.0", "", &commits()[..1]);
+        assert_eq!(output, "1.2.0");
+    }
+
+    #[test]
+    fn prepends_below_title() {
+        let existing = "# Changelog\n\nNotes.\n\n## 1.1.0\n\n- old\n";
+        assert_eq!(
+            prepend(existing, "## 1.2.0\n\n- new\n"),
+            "# Changelog\n\nNotes.\n\n## 1.2.0\n\n- new\n\n## 1.1.0\n\n- old\n"
+        );
+        assert_eq!(prepend("", "## 1.2.0\n"), "# Changelog\n\n## 1.2.0\n");
+        assert_eq!(
+            prepend("# Changelog\n", "## 1.2.0\n"),
+            "# Changelog\n\n## 1.2.0\n"
+        );
+    }
+
+    const KEEP_A_CHANGELOG: &str = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- New flag.
+
+## [1.1.0] - 2026-01-02
+
+- Old entry.
+
+[Unreleased]: https://github.com/o/r/compare/v1.1.0...HEAD
+[1.1.0]: https://github.com/o/r/compare/v1.0.0...v1.1.0
+";
+
+    #[test]
+    fn rolls_over_unreleased_section() {
+        assert_eq!(
+            rollover(KEEP_A_CHANGELOG, "1.2.0", "2026-10-18").unwrap(),
+            "# Changelog
+
+## [Unreleased]
+
+## [1.2.0] - 2026-10-18
+
+### Added
+
+- New flag.
+
+## [1.1.0] - 2026-01-02
+
+- Old entry.
+
+[Unreleased]: https://github.com/o/r/compare/v1.2.0...HEAD
+[1.2.0]: https://github.com/o/r/compare/v1.1.0...v1.2.0
+[1.1.0]: https://github.com/o/r/compare/v1.0.0...v1.1.0
+"
+        );
+        assert_eq!(rollover("# Changelog\n\n## 1.1.0\n", "1.2.0", ""), None);
+    }
+
+    #[test]
+    fn adds_generated_release_below_unreleased_entries() {
+        let section = render(DEFAULT_TEMPLATE, "1.2.0", "2026-10-18", &commits()[1..2]);
+        assert_eq!(
+            add_release(KEEP_A_CHANGELOG, &section, "1.2.0", "2026-10-18"),
+            "# Changelog
+
+## [Unreleased]
+
+## [1.2.0] - 2026-10-18
+
+### Added
+
+- New flag.
+
+### Bug Fixes
+
+- handle empty tags (3f2a9c1)
+
+## [1.1.0] - 2026-01-02
+
+- Old entry.
+
+[Unreleased]: https://github.com/o/r/compare/v1.2.0...HEAD
+[1.2.0]: https://github.com/o/r/compare/v1.1.0...v1.2.0
+[1.1.0]: https://github.com/o/r/compare/v1.0.0...v1.1.0
+"
+        );
+        assert_eq!(
+            add_release("# Changelog\n\n## 1.1.0\n", &section, "1.2.0", "2026-10-18"),
+            prepend("# Changelog\n\n## 1.1.0\n", &section)
+        );
+    }
+
+    #[test]
+    fn extracts_release_notes() {
+        assert_eq!(
+            release_notes(KEEP_A_CHANGELOG, "1.1.0"),
+            Some(String::from("- Old entry."))
+        );
+        let rendered = render(DEFAULT_TEMPLATE, "1.2.0", "2026-10-18", &commits()[1..2]);
+        assert_eq!(
+            release_notes(&prepend(KEEP_A_CHANGELOG, &rendered), "1.2.0"),
+            Some(String::from(
+                "### Bug Fixes\n\n- handle empty tags (3f2a9c1)"
+            ))
+        );
+        assert_eq!(
+            release_notes("## v2.0.0\n\n- Big.\n", "2.0.0"),
+            Some(String::from("- Big."))
+        );
+        assert_eq!(release_notes(KEEP_A_CHANGELOG, "1.0.0"), None);
+        assert_eq!(release_notes("## [1.0.0]\n\n## [0.9.0]\n", "1.0.0"), None);
+    }
+
+    #[test]
+    fn detects_empty_unreleased_section() {
+        assert!(!unreleased_is_empty(KEEP_A_CHANGELOG));
+        assert!(unreleased_is_empty(
+            "## [Unreleased]\n\n### Added\n\n## [1.0.0]\n\n- a\n"
+        ));
+        assert!(unreleased_is_empty(
+            "## [Unreleased]\n\n[Unreleased]: https://x/compare/1.0.0...HEAD\n"
+        ));
+        assert!(!unreleased_is_empty("## 1.0.0\n"));
+    }
+}
@@ -0,0 +1,280 @@
+use config::ModifierType;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+pub const DIRECTORY: &str = ".changeset";
+
+/// The bump level a changeset asks for, ordered from smallest to largest.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Level {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Level {
+    /// A major changeset is a breaking change, which bumps the minor version of a `0.y.z`
+    /// crate instead of refusing like `major` does.
+    pub fn mod_type(self) -> ModifierType {
+        match self {
+            Level::Patch => ModifierType::Patch,
+            Level::Minor => ModifierType::Minor,
+            Level::Major => ModifierType::Breaking,
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Level::Patch => "Patch Changes",
+            Level::Minor => "Minor Changes",
+            Level::Major => "Major Changes",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+    fn from_str(input: &str) -> Result<Level, Self::Err> {
+        match input {
+            "patch" => Ok(Level::Patch),
+            "minor" => Ok(Level::Minor),
+            "major" => Ok(Level::Major),
+            _ => Err(format!(
+                "Unknown bump level `{}`, expected major, minor or patch",
+                input
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Level::Patch => "patch",
+            Level::Minor => "minor",
+            Level::Major => "major",
+        })
+    }
+}
+
+/// A pending release intent recorded in `.changeset/<name>.md`.
+#[derive(Debug, PartialEq)]
+pub struct Changeset {
+    pub releases: Vec<(String, Level)>,
+    pub summary: String,
+}
+
+impl Changeset {
+    /// Parses a changeset file: front matter mapping crate names to levels, then a summary.
+    pub fn parse(contents: &str) -> Result<Changeset, String> {
+        let rest = contents
+            .trim_start()
+            .strip_prefix("---")
+            .ok_or_else(|| String::from("missing `---` front matter"))?;
+        let end = rest
+            .find("\n---")
+            .ok_or_else(|| String::from("unterminated front matter"))?;
+        let mut releases = Vec::new();
+        for line in rest[..end].lines().filter(|line| !line.trim().is_empty()) {
+            let colon = line
+                .rfind(':')
+                .ok_or_else(|| format!("expected `\"crate\": level`, found `{}`", line))?;
+            let name = line[..colon].trim().trim_matches(|c| c == '"' || c == '\'');
+            let level = line[colon + 1..].trim().parse()?;
+            releases.push((name.to_string(), level));
+        }
+        if releases.is_empty() {
+            return Err(String::from("front matter names no crates"));
+        }
+        Ok(Changeset {
+            releases,
+            summary: rest[end + 4..].trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Changeset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "---")?;
+        for &(ref name, level) in &self.releases {
+            writeln!(f, "\"{}\": {}", name, level)?;
+        }
+        writeln!(f, "---")?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.summary.trim())
+    }
+}
+
+/// Writes `changeset` to a new randomly named file in the `.changeset` directory under `root`.
+pub fn write(root: &Path, changeset: &Changeset) -> PathBuf {
+    let directory = root.join(DIRECTORY);
+    fs::create_dir_all(&directory).expect("create the .changeset directory");
+    loop {
+        let path = directory.join(format!("{:016x}.md", random()));
+        if !path.exists() {
+            fs::write(&path, changeset.to_string()).expect("write the changeset");
+            return path;
+        }
+    }
+}
+
+fn random() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    hasher.finish()
+}
+
+/// Reads every pending changeset under `root`, sorted by file name.
+pub fn read_all(root: &Path) -> Vec<(PathBuf, Changeset)> {
+    let directory = root.join(DIRECTORY);
+    if !directory.is_dir() {
+        return Vec::new();
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+        .expect("read the .changeset directory")
+        .map(|entry| entry.expect("read a .changeset entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let contents = fs::read_to_string(&path).expect("read the changeset");
+            let changeset = Changeset::parse(&contents)
+                .unwrap_or_else(|err| panic!("Invalid changeset {}: {}", path.display(), err));
+            (path, changeset)
+        })
+        .collect()
+}
+
+/// A crate's pending release: the highest requested level and the summaries to list.
+#[derive(Debug, PartialEq)]
+pub struct Release<'a> {
+    pub level: Level,
+    pub summaries: Vec<(Level, &'a str)>,
+}
+
+/// Combines changesets into one release per crate.
+pub fn plan<'a, I>(changesets: I) -> BTreeMap<String, Release<'a>>
+where
+    I: IntoIterator<Item = &'a Changeset>,
+{
+    let mut releases: BTreeMap<String, Release> = BTreeMap::new();
+    for changeset in changesets {
+        for &(ref name, level) in &changeset.releases {
+            let release = releases.entry(name.clone()).or_insert(Release {
+                level,
+                summaries: Vec::new(),
+            });
+            release.level = release.level.max(level);
+            release.summaries.push((level, changeset.summary.as_str()));
+        }
+    }
+    releases
+}
+
+/// Renders the changelog section for one crate's release.
+pub fn changelog_section(version: &str, release: &Release) -> String {
+    let mut section = format!("## {}\n", version);
+    for &level in &[Level::Major, Level::Minor, Level::Patch] {
+        let entries: Vec<String> = release
+            .summaries
+            .iter()
+            .filter(|&&(summary_level, _)| summary_level == level)
+            .map(|&(_, summary)| format!("- {}", summary.replace('\n', "\n  ")))
+            .collect();
+        if !entries.is_empty() {
+            section.push_str(&format!(
+                "\n### {}\n\n{}\n",
+                level.heading(),
+                entries.join("\n")
+            ));
+        }
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::VersionModifier;
+    use semver::Version;
+    use version;
+
+    const CHANGESET: &str = "---
+\"foo\": minor
+\"bar\": patch
+---
+
+Add the `--frobnicate` flag.
+";
+
+    #[test]
+    fn parses_and_renders() {
+        let changeset = Changeset::parse(CHANGESET).unwrap();
+        assert_eq!(
+            changeset,
+            Changeset {
+                releases: vec![
+                    (String::from("foo"), Level::Minor),
+                    (String::from("bar"), Level::Patch),
+                ],
+                summary: String::from("Add the `--frobnicate` flag."),
+            }
+        );
+        assert_eq!(changeset.to_string(), CHANGESET);
+    }
+
+    #[test]
+    fn rejects_malformed_changesets() {
+        assert!(Changeset::parse("no front matter").is_err());
+        assert!(Changeset::parse("---\nfoo: minor\n").is_err());
+        assert!(Changeset::parse("---\n---\nsummary").is_err());
+        assert!(Changeset::parse("---\nfoo: huge\n---\nsummary").is_err());
+    }
+
+    #[test]
+    fn major_changesets_respect_0x_versions() {
+        let bumped = |current: &str| {
+            let mut version = Version::parse(current).unwrap();
+            version::update_version(
+                &mut version,
+                VersionModifier::from_mod_type(Level::Major.mod_type()),
+            );
+            version.to_string()
+        };
+        assert_eq!(bumped("0.1.0"), "0.2.0");
+        assert_eq!(bumped("0.0.3"), "0.0.4");
+        assert_eq!(bumped("1.4.2"), "2.0.0");
+    }
+
+    #[test]
+    fn plans_highest_level_per_crate() {
+        let first = Changeset::parse(CHANGESET).unwrap();
+        let second = Changeset::parse("---\nfoo: patch\nbar: major\n---\nFix it.").unwrap();
+        let plan = plan(vec![&first, &second]);
+        assert_eq!(plan["foo"].level, Level::Minor);
+        assert_eq!(plan["bar"].level, Level::Major);
+        assert_eq!(
+            changelog_section("2.0.0", &plan["bar"]),
+            "## 2.0.0
+
+### Major Changes
+
+- Fix it.
+
+### Patch Changes
+
+- Add the `--frobnicate` flag.
+"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use semver::{Version, VersionReq};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+use version::Change;
+
+/// How far apart two versions are under Cargo's compatibility rules, where the left-most
+/// non-zero position is the major one: in `0.y.z` a change to `y` is major and one to `z`
+/// is minor, and every change to `0.0.z` is major.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difference {
+    Major,
+    Minor,
+    Patch,
+    Pre,
+    Build,
+    None,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Difference::Major => "major",
+            Difference::Minor => "minor",
+            Difference::Patch => "patch",
+            Difference::Pre => "pre",
+            Difference::Build => "build",
+            Difference::None => "none",
+        })
+    }
+}
+
+impl Difference {
+    /// The largest kind of change a release with this difference may contain.
+    pub fn allows(self) -> Change {
+        match self {
+            Difference::Major => Change::Breaking,
+            Difference::Minor => Change::Feature,
+            _ => Change::Fix,
+        }
+    }
+}
+
+/// The relationship between an old and a new version.
+#[derive(Debug, PartialEq)]
+pub struct Comparison {
+    pub old: Version,
+    pub new: Version,
+    /// The precedence of `new` relative to `old`, which ignores build metadata.
+    pub precedence: Ordering,
+    /// Whether the caret requirement `^old` accepts `new`.
+    pub caret_compatible: bool,
+    pub difference: Difference,
+}
+
+impl Comparison {
+    pub fn new(old: Version, new: Version) -> Comparison {
+        let requirement = VersionReq::parse(&format!("^{}", old)).expect("caret requirement");
+        Comparison {
+            precedence: new.cmp(&old),
+            caret_compatible: requirement.matches(&new),
+            difference: difference(&old, &new),
+            old,
+            new,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "old": self.old.to_string(),
+            "new": self.new.to_string(),
+            "precedence": match self.precedence {
+                Ordering::Less => "older",
+                Ordering::Equal => "equal",
+                Ordering::Greater => "newer",
+            },
+            "caret_compatible": self.caret_compatible,
+            "difference": self.difference.to_string(),
+            "breaking": self.difference == Difference::Major,
+        })
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.precedence {
+            Ordering::Less => writeln!(f, "{} is older than {}", self.new, self.old)?,
+            Ordering::Equal => writeln!(f, "{} has the same precedence as {}", self.new, self.old)?,
+            Ordering::Greater => writeln!(f, "{} is newer than {}", self.new, self.old)?,
+        }
+        writeln!(
+            f,
+            "^{} {} {}",
+            self.old,
+            if self.caret_compatible {
+                "accepts"
+            } else {
+                "does not accept"
+            },
+            self.new
+        )?;
+        match self.difference {
+            Difference::None => write!(f, "The versions are identical"),
+            Difference::Major => write!(f, "This is a major change, breaking under Cargo's rules"),
+            difference => write!(f, "This is a {} change", difference),
+        }
+    }
+}
+
+fn difference(old: &Version, new: &Version) -> Difference {
+    let old_parts = [old.major, old.minor, old.patch];
+    let new_parts = [new.major, new.minor, new.patch];
+    match old_parts
+        .iter()
+        .zip(new_parts.iter())
+        .position(|(a, b)| a != b)
+    {
+        Some(changed) => {
+            // The breaking position is the left-most one that is non-zero in either version.
+            let breaking = old_parts
+                .iter()
+                .zip(new_parts.iter())
+                .position(|(&a, &b)| a != 0 || b != 0)
+                .unwrap_or(2);
+            if changed <= breaking {
+                Difference::Major
+            } else if changed == breaking + 1 {
+                Difference::Minor
+            } else {
+                Difference::Patch
+            }
+        }
+        None if old.pre != new.pre => Difference::Pre,
+        None if old.build != new.build => Difference::Build,
+        None => Difference::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare(old: &str, new: &str) -> Comparison {
+        Comparison::new(Version::parse(old).unwrap(), Version::parse(new).unwrap())
+    }
+
+    #[test]
+    fn labels_differences_under_cargo_rules() {
+        let cases = [
+            ("1.2.3", "2.0.0", Difference::Major),
+            ("1.2.3", "1.3.0", Difference::Minor),
+            ("1.2.3", "1.2.4", Difference::Patch),
+            ("0.9.3", "0.10.0", Difference::Major),
+            ("0.9.3", "0.9.4", Difference::Minor),
+            ("0.0.3", "0.0.4", Difference::Major),
+            ("0.9.3", "1.0.0", Difference::Major),
+            ("1.2.3", "1.2.2", Difference::Patch),
+            ("1.2.3-rc.1", "1.2.3", Difference::Pre),
+            ("1.2.3+a", "1.2.3+b", Difference::Build),
+            ("1.2.3", "1.2.3", Difference::None),
+        ];
+        for &(old, new, expected) in &cases {
+            assert_eq!(compare(old, new).difference, expected, "{} -> {}", old, new);
+        }
+    }
+
+    #[test]
+    fn checks_precedence_and_caret() {
+        let comparison = compare("0.9.3", "0.10.0");
+        assert_eq!(comparison.precedence, Ordering::Greater);
+        assert!(!comparison.caret_compatible);
+
+        let comparison = compare("1.2.3", "1.9.0");
+        assert!(comparison.caret_compatible);
+
+        let comparison = compare("1.2.3", "1.2.3-rc.1");
+        assert_eq!(comparison.precedence, Ordering::Less);
+        assert!(!comparison.caret_compatible);
+
+        let comparison = compare("1.2.3+a", "1.2.3+b");
+        assert_eq!(comparison.precedence, Ordering::Equal);
+        assert!(comparison.caret_compatible);
+    }
+
+    #[test]
+    fn renders_json() {
+        assert_eq!(
+            compare("0.9.3", "0.10.0").to_json(),
+            json!({
+                "old": "0.9.3",
+                "new": "0.10.0",
+                "precedence": "newer",
+                "caret_compatible": false,
+                "difference": "major",
+                "breaking": true,
+            })
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use api::ApiChange;
+use std::collections::BTreeSet;
+use toml_edit::{Document, Item};
+use version::Change;
+
+/// Compares the compatibility-relevant parts of two manifests: the features, which features
+/// are on by default, `rust-version` and the requirements on `public = true` dependencies.
+pub fn diff(old: &Document, new: &Document) -> Vec<ApiChange> {
+    let mut changes = Vec::new();
+    let new_features = features(new);
+    for feature in features(old).difference(&new_features) {
+        changes.push(ApiChange {
+            change: Change::Breaking,
+            description: format!("removed feature `{}`", feature),
+        });
+    }
+
+    let new_defaults = default_features(new);
+    for feature in default_features(old).difference(&new_defaults) {
+        if new_features.contains(feature) {
+            changes.push(ApiChange {
+                change: Change::Breaking,
+                description: format!("feature `{}` is no longer on by default", feature),
+            });
+        }
+    }
+
+    let old_msrv = old["package"]["rust-version"].as_str();
+    let new_msrv = new["package"]["rust-version"].as_str();
+    if let (Some(old_msrv), Some(new_msrv)) = (old_msrv, new_msrv) {
+        if version_parts(new_msrv) > version_parts(old_msrv) {
+            changes.push(ApiChange {
+                change: Change::Feature,
+                description: format!("raised rust-version from {} to {}", old_msrv, new_msrv),
+            });
+        }
+    }
+
+    if let Some(dependencies) = new["dependencies"].as_table_like() {
+        for (name, dependency) in dependencies.iter() {
+            if dependency["public"].as_bool() != Some(true) {
+                continue;
+            }
+            let old_requirement = requirement(&old["dependencies"][name]);
+            if let (Some(old_requirement), Some(new_requirement)) =
+                (old_requirement, requirement(dependency))
+            {
+                if compatible_range(old_requirement) != compatible_range(new_requirement) {
+                    changes.push(ApiChange {
+                        change: Change::Breaking,
+                        description: format!(
+                            "public dependency `{}` moved from {} to {}",
+                            name, old_requirement, new_requirement
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// The features a manifest offers, including the implicit ones of optional dependencies.
+fn features(manifest: &Document) -> BTreeSet<String> {
+    let mut features = BTreeSet::new();
+    if let Some(table) = manifest["features"].as_table_like() {
+        features.extend(
+            table
+                .iter()
+                .filter(|&(name, _)| name != "default")
+                .map(|(name, _)| name.to_string()),
+        );
+    }
+    if let Some(dependencies) = manifest["dependencies"].as_table_like() {
+        features.extend(
+            dependencies
+                .iter()
+                .filter(|&(_, dependency)| dependency["optional"].as_bool() == Some(true))
+                .map(|(name, _)| name.to_string()),
+        );
+    }
+    features
+}
+
+fn default_features(manifest: &Document) -> BTreeSet<String> {
+    manifest["features"]["default"]
+        .as_array()
+        .map(|defaults| {
+            defaults
+                .iter()
+                .filter_map(|feature| feature.as_str())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The version requirement of a dependency written as `"1.0"` or `{ version = "1.0" }`.
+fn requirement(dependency: &Item) -> Option<&str> {
+    dependency
+        .as_str()
+        .or_else(|| dependency["version"].as_str())
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| part.trim().parse().unwrap_or(0))
+        .collect()
+}
+
+/// The leading version of a requirement up to its left-most non-zero component, which is
+/// what Cargo considers compatible: `^1.2` gives `[1]` and `0.9.3` gives `[0, 9]`.
+fn compatible_range(requirement: &str) -> Vec<u64> {
+    let first = requirement.split(',').next().unwrap_or(requirement);
+    let mut parts = version_parts(first.trim_start_matches(|c: char| !c.is_ascii_digit()));
+    if let Some(non_zero) = parts.iter().position(|&part| part != 0) {
+        parts.truncate(non_zero + 1);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptions(old: &str, new: &str) -> Vec<(Change, String)> {
+        diff(&old.parse().unwrap(), &new.parse().unwrap())
+            .into_iter()
+            .map(|change| (change.change, change.description))
+            .collect()
+    }
+
+    const OLD: &str = r#"
+[package]
+name = "foo"
+rust-version = "1.56"
+
+[features]
+default = ["std", "color"]
+std = []
+color = []
+unstable = []
+
+[dependencies]
+serde = { version = "1.0", public = true }
+rand = { version = "0.7", public = true }
+log = "0.4"
+regex = { version = "1", optional = true }
+"#;
+
+    #[test]
+    fn unchanged_manifest_has_no_changes() {
+        assert_eq!(descriptions(OLD, OLD), Vec::new());
+    }
+
+    #[test]
+    fn flags_breaking_manifest_changes() {
+        let new = r#"
+[package]
+name = "foo"
+rust-version = "1.60"
+
+[features]
+default = ["std"]
+std = []
+color = []
+
+[dependencies]
+serde = { version = "1.0.100", public = true }
+rand = { version = "0.8", public = true }
+log = "0.5"
+"#;
+        assert_eq!(
+            descriptions(OLD, new),
+            vec![
+                (Change::Breaking, String::from("removed feature `regex`")),
+                (Change::Breaking, String::from("removed feature `unstable`")),
+                (
+                    Change::Breaking,
+                    String::from("feature `color` is no longer on by default")
+                ),
+                (
+                    Change::Feature,
+                    String::from("raised rust-version from 1.56 to 1.60")
+                ),
+                (
+                    Change::Breaking,
+                    String::from("public dependency `rand` moved from 0.7 to 0.8")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_compatible_ranges() {
+        assert_eq!(compatible_range("^1.2"), vec![1]);
+        assert_eq!(compatible_range("0.9.3"), vec![0, 9]);
+        assert_eq!(compatible_range("=0.0.3"), vec![0, 0, 3]);
+        assert_eq!(compatible_range(">= 2.1, < 3"), vec![2]);
+    }
+}
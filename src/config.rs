@@ -1,14 +1,125 @@
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-use cargo_metadata::MetadataCommand;
-use clap::{App, AppSettings, Arg, ArgMatches};
+use build_template::{self, BuildContext};
+use calver::CalVerFormat;
+use cargo_metadata::{Metadata, MetadataCommand};
+use changeset::Level;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use git::{self, CommitMessage, CommitMode, DirtyPolicy, Release, Signing, TagMode};
 use semver::{Identifier, SemVerError, Version};
-use std::path::PathBuf;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use sync::{self, Replacement};
+use version;
 
-pub fn get_config() -> Config {
+pub enum Command {
+    Bump(Config),
+    CheckSync(Config),
+    AddChangeset {
+        workspace: Workspace,
+        crates: Vec<String>,
+        level: Level,
+        summary: String,
+    },
+    ApplyChangesets {
+        workspace: Workspace,
+        /// The message to commit the bumped crates with, if they should be committed.
+        commit: Option<CommitMessage>,
+    },
+    Verify {
+        workspace: Workspace,
+        base: String,
+    },
+    Suggest {
+        conf: Config,
+        apply: bool,
+    },
+    Compare {
+        old: Version,
+        new: Version,
+        json: bool,
+    },
+}
+
+pub fn get_command() -> Command {
     let matches = build_cli_parser().get_matches();
-    Config::from_matches(matches)
+    match matches.subcommand() {
+        ("check-sync", _) => Command::CheckSync(Config::from_matches(matches)),
+        ("verify", Some(verify)) => Command::Verify {
+            workspace: Workspace::from_matches(&matches),
+            base: verify.value_of("base").expect("base").to_string(),
+        },
+        ("suggest", Some(suggest)) => {
+            let apply = suggest.is_present("apply");
+            Command::Suggest {
+                conf: Config::from_matches(matches),
+                apply,
+            }
+        }
+        ("add", Some(add)) => {
+            let workspace = Workspace::from_matches(&matches);
+            let crates = match add.values_of("crate") {
+                Some(crates) => crates.map(String::from).collect(),
+                None if workspace.members.len() == 1 => vec![workspace.members[0].name.clone()],
+                None => panic!("This is a workspace, name the crates to bump with --crate."),
+            };
+            Command::AddChangeset {
+                workspace,
+                crates,
+                level: add
+                    .value_of("level")
+                    .expect("level")
+                    .parse()
+                    .expect("valid level"),
+                summary: add.value_of("summary").expect("summary").to_string(),
+            }
+        }
+        ("version", Some(version)) => Command::ApplyChangesets {
+            workspace: Workspace::from_matches(&matches),
+            commit: if version.is_present("commit") {
+                Some(commit_message(version, &Value::Null))
+            } else {
+                None
+            },
+        },
+        ("compare", Some(compare)) => {
+            let version = |name| {
+                let value = compare.value_of(name).expect(name);
+                Version::parse(value.trim_start_matches('v'))
+                    .unwrap_or_else(|err| panic!("Invalid version {}: {}", value, err))
+            };
+            Command::Compare {
+                old: version("old"),
+                new: version("new"),
+                json: compare.is_present("json"),
+            }
+        }
+        _ => Command::Bump(Config::from_matches(matches)),
+    }
+}
+
+fn set_arg<'a, 'b>(name: &'a str, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .long(name)
+        .value_name("N")
+        .takes_value(true)
+        .conflicts_with("version")
+        .help(help)
+}
+
+/// The arguments shaping the release commit message.
+fn message_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+    [
+        Arg::with_name("trailers").long("trailers").help(
+            "Optional add Release-Crate, Release-Version and Previous-Version trailers to \
+             the release commit.",
+        ),
+        Arg::with_name("skip-ci")
+            .long("skip-ci")
+            .help("Optional mark the release commit with [skip ci]."),
+    ]
 }
 
 fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
@@ -16,7 +127,7 @@ fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
         .version(VERSION)
         .author("Wraithan McCarroll <xwraithanx@gmail.com>")
         .usage(
-            "cargo bump [<version> | major | minor | patch] [FLAGS]
+            "cargo bump [<version> | major | minor | patch | breaking | feature | fix | calver | auto | snapshot] [FLAGS]
 
     Version parts: ${MAJOR}.${MINOR}.${PATCH}-${PRE-RELEASE}+${BUILD}
     Example: 3.1.4-alpha+159",
@@ -42,7 +153,16 @@ fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
         )
         .arg(Arg::with_name("version").index(2).help(
             "Version should be a semver (https://semver.org/) string or the \
-             position of the current version to increment: major, minor or patch.",
+             position of the current version to increment: major, minor or patch. \
+             Add +N to increment by more than one, such as minor+2, and :keep-pre or \
+             :keep-build to carry the current pre-release or build metadata over, such as \
+             major:keep-pre. \
+             Use breaking, feature or fix to pick the position by Cargo's compatibility \
+             rules, so a breaking change to 0.y.z bumps the minor version. \
+             Use calver for calendar versioning based on today's date. \
+             Use auto to pick the position from the Conventional Commits since the last tag. \
+             Use snapshot for a development version derived from the last tag, such as \
+             1.4.1-dev.17+g3f2a9c1.",
         ))
         .arg(
             Arg::with_name("pre-release")
@@ -50,7 +170,10 @@ fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
                 .long("pre-release")
                 .value_name("RELEASE TYPE")
                 .takes_value(true)
-                .help("Optional pre-release information."),
+                .help(
+                    "Optional pre-release information. Use branch to derive it from the \
+                     current git branch, numbered after the existing tags.",
+                ),
         )
         .arg(
             Arg::with_name("build-metadata")
@@ -58,7 +181,53 @@ fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
                 .long("build")
                 .value_name("BUILD")
                 .takes_value(true)
-                .help("Optional build metadata for this version."),
+                .help(
+                    "Optional build metadata for this version. May use {date}, {short_sha}, \
+                     {dirty} and {ci_run}, or git as a shorthand for {date}.{short_sha}.",
+                ),
+        )
+        .arg(
+            Arg::with_name("calver-format")
+                .long("calver-format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .help(
+                    "Optional calendar versioning format, made of YYYY, YY, MM, WW, DD and \
+                     MICRO, or 0Y, 0M, 0W and 0D for the same unpadded numbers. Defaults to \
+                     YYYY.MM.MICRO.",
+                ),
+        )
+        .arg(
+            Arg::with_name("graduate")
+                .long("graduate")
+                .help("Optional release 1.0.0 from a 0.y.z version, used with major."),
+        )
+        .arg(set_arg(
+            "set-major",
+            "Optional set the major version, keeping the other positions.",
+        ))
+        .arg(set_arg(
+            "set-minor",
+            "Optional set the minor version, keeping the other positions.",
+        ))
+        .arg(set_arg(
+            "set-patch",
+            "Optional set the patch version, keeping the other positions.",
+        ))
+        .arg(
+            Arg::with_name("allow-downgrade")
+                .long("allow-downgrade")
+                .help("Optional allow a version that doesn't come after the current one."),
+        )
+        .arg(Arg::with_name("strict").long("strict").help(
+            "Optional refuse to bump when Cargo.toml changed since the last tag in a way \
+             that needs a larger bump, instead of warning.",
+        ))
+        .arg(
+            Arg::with_name("print-next")
+                .long("print-next")
+                .conflicts_with_all(&["git-tag", "commit", "amend", "tag-only"])
+                .help("Optional print the new version without changing any files."),
         )
         .arg(
             Arg::with_name("git-tag")
@@ -66,37 +235,507 @@ fn build_cli_parser<'a, 'b>() -> App<'a, 'b> {
                 .long("git-tag")
                 .help("Optional commit the updated version and create a git tag."),
         )
+        .arg(
+            Arg::with_name("commit")
+                .long("commit")
+                .conflicts_with("git-tag")
+                .help("Optional commit the updated version without tagging it."),
+        )
+        .arg(
+            Arg::with_name("amend")
+                .long("amend")
+                .conflicts_with("commit")
+                .help(
+                    "Optional fold the updated version into the last commit instead of making a \
+                     new one, and tag it with --git-tag.",
+                ),
+        )
+        .arg(
+            Arg::with_name("tag-only")
+                .long("tag-only")
+                .conflicts_with_all(&[
+                    "version",
+                    "git-tag",
+                    "commit",
+                    "amend",
+                    "changelog",
+                    "set-major",
+                    "set-minor",
+                    "set-patch",
+                ])
+                .help(
+                    "Optional tag HEAD with the version its Cargo.toml already has, without \
+                     changing any files.",
+                ),
+        )
+        .arg(
+            Arg::with_name("lightweight")
+                .long("lightweight")
+                .conflicts_with("sign")
+                .help("Optional create a lightweight instead of an annotated tag."),
+        )
+        .arg(
+            Arg::with_name("allow-dirty")
+                .long("allow-dirty")
+                .help("Optional release with uncommitted changes, leaving them out of the commit."),
+        )
+        .arg(
+            Arg::with_name("allow-untracked")
+                .long("allow-untracked")
+                .help("Optional release with untracked files in the working directory."),
+        )
+        .arg(
+            Arg::with_name("push")
+                .long("push")
+                .value_name("REMOTE")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .help(
+                    "Optional push the release commit and tag, to the branch's upstream remote \
+                     or origin unless REMOTE or push-remote in [package.metadata.bump] is given.",
+                ),
+        )
+        .arg(
+            Arg::with_name("sign").long("sign").help(
+                    "Optional sign the release commit and tag, with signing-key from \
+                     [package.metadata.bump] or git's user.signingkey.",
+                ),
+        )
+        .args(&message_args())
+        .arg(Arg::with_name("changelog").long("changelog").help(
+            "Optional prepend a CHANGELOG.md section built from the Conventional \
+             Commits since the last tag.",
+        ))
+        .arg(
+            Arg::with_name("allow-empty-changelog")
+                .long("allow-empty-changelog")
+                .help("Optional release even if the Unreleased section of CHANGELOG.md is empty."),
+        )
+        .arg(
+            Arg::with_name("notes-file")
+                .long("notes-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .conflicts_with("print-next")
+                .help(
+                    "Optional write the CHANGELOG.md section of the new version to PATH, as \
+                     also used for the annotated tag message.",
+                ),
+        )
+        .arg(
+            Arg::with_name("changelog-template")
+                .long("changelog-template")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("changelog")
+                .help("Optional template for the generated changelog section."),
+        )
+        .subcommand(SubCommand::with_name("check-sync").about(
+            "Checks that the README, html_root_url, replacement files and latest git tag \
+             agree with the version in Cargo.toml.",
+        ))
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("Records the intent to release crates in a new .changeset file.")
+                .arg(
+                    Arg::with_name("level")
+                        .index(1)
+                        .required(true)
+                        .possible_values(&["major", "minor", "patch"])
+                        .help("Position of the version to increment."),
+                )
+                .arg(
+                    Arg::with_name("summary")
+                        .index(2)
+                        .required(true)
+                        .help("Summary of the change for the changelog."),
+                )
+                .arg(
+                    Arg::with_name("crate")
+                        .short("c")
+                        .long("crate")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Crate to release, required in workspaces."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("version")
+                .about(
+                    "Applies and deletes the pending .changeset files, bumping each crate by \
+                     the highest level requested for it and updating its CHANGELOG.md.",
+                )
+                .arg(
+                    Arg::with_name("commit")
+                        .long("commit")
+                        .help("Optional commit the bumped crates in one release commit."),
+                )
+                .args(&message_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about(
+                    "Fails unless every crate changed since the base ref has a higher version \
+                     than at the base ref.",
+                )
+                .arg(
+                    Arg::with_name("base")
+                        .long("base")
+                        .value_name("REF")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The ref to compare with, such as origin/main."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("suggest")
+                .about(
+                    "Suggests the position to increment by comparing the public API with the \
+                     one at the last release tag.",
+                )
+                .arg(
+                    Arg::with_name("apply")
+                        .long("apply")
+                        .help("Optional bump the version as suggested."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about(
+                    "Compares two versions, reporting their precedence, whether a caret \
+                     requirement on the old one accepts the new one and how large the change \
+                     is under Cargo's compatibility rules.",
+                )
+                .arg(
+                    Arg::with_name("old")
+                        .index(1)
+                        .required(true)
+                        .help("The version being upgraded from."),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .index(2)
+                        .required(true)
+                        .help("The version being upgraded to."),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Optional print the comparison as JSON."),
+                ),
+        )
 }
 
 pub struct Config {
     pub version_modifier: VersionModifier,
     pub manifest: PathBuf,
-    pub git_tag: bool,
+    pub crate_name: String,
+    pub lib_path: Option<PathBuf>,
+    pub readme: Option<PathBuf>,
+    /// How the release is recorded in git, if at all.
+    pub git: Option<Release>,
+    pub print_next: bool,
+    /// Only the manifest is written for snapshots, which aren't releases.
+    pub snapshot: bool,
+    pub strict: bool,
+    pub changelog: bool,
+    pub changelog_template: Option<PathBuf>,
+    pub allow_empty_changelog: bool,
+    pub notes_file: Option<PathBuf>,
+    pub dirty: DirtyPolicy,
+    pub release_branches: Vec<String>,
+    /// Other files mentioning the version, rewritten by bump and checked by check-sync.
+    pub replacements: Vec<Replacement>,
+    /// The remote to push the release to, if it should be pushed.
+    pub push: Option<String>,
+}
+
+pub struct Member {
+    pub name: String,
+    pub manifest: PathBuf,
+}
+
+pub struct Workspace {
+    pub root: PathBuf,
+    pub members: Vec<Member>,
+}
+
+impl Workspace {
+    fn from_matches(matches: &ArgMatches) -> Workspace {
+        let metadata = read_metadata(matches);
+        Workspace {
+            members: metadata
+                .workspace_members
+                .iter()
+                .map(|id| Member {
+                    name: metadata[id].name.clone(),
+                    manifest: metadata[id].manifest_path.clone(),
+                })
+                .collect(),
+            root: metadata.workspace_root,
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|member| member.name == name)
+    }
+}
+
+/// Reads `--allow-dirty`, `--allow-untracked` and the `ignore-dirty` globs and
+/// `allow-untracked` flag from `[package.metadata.bump]`.
+fn dirty_policy(matches: &ArgMatches, metadata: &Value) -> DirtyPolicy {
+    let settings = &metadata["bump"];
+    DirtyPolicy {
+        allow_dirty: matches.is_present("allow-dirty"),
+        allow_untracked: matches.is_present("allow-untracked")
+            || settings["allow-untracked"].as_bool() == Some(true),
+        ignore: metadata_strings(&settings["ignore-dirty"]).unwrap_or_default(),
+    }
+}
+
+/// Reads how to record the release in git from `--git-tag`, `--commit`, `--amend`,
+/// `--tag-only`, `--lightweight` and `--sign`, with the `signing-key` from
+/// `[package.metadata.bump]`.
+fn release(matches: &ArgMatches, metadata: &Value) -> Option<Release> {
+    let tag_only = matches.is_present("tag-only");
+    let commit = if matches.is_present("amend") {
+        CommitMode::Amend
+    } else if matches.is_present("git-tag") || matches.is_present("commit") {
+        CommitMode::New
+    } else if tag_only {
+        CommitMode::Skip
+    } else {
+        for flag in &["push", "sign", "lightweight"] {
+            if matches.is_present(flag) {
+                panic!(
+                    "--{} needs one of --git-tag, --commit, --amend or --tag-only.",
+                    flag
+                );
+            }
+        }
+        return None;
+    };
+    let tag = if !(matches.is_present("git-tag") || tag_only) {
+        if matches.is_present("lightweight") {
+            panic!("--lightweight needs --git-tag or --tag-only.");
+        }
+        TagMode::Skip
+    } else if matches.is_present("lightweight") {
+        TagMode::Lightweight
+    } else {
+        TagMode::Annotated
+    };
+    let sign = if matches.is_present("sign") {
+        Some(Signing {
+            key: metadata["bump"]["signing-key"].as_str().map(String::from),
+        })
+    } else {
+        None
+    };
+    Some(Release {
+        commit,
+        tag,
+        sign,
+        message: commit_message(matches, metadata),
+    })
+}
+
+/// Reads `--trailers` and `--skip-ci`, or `trailers` and `skip-ci` in
+/// `[package.metadata.bump]`.
+fn commit_message(matches: &ArgMatches, metadata: &Value) -> CommitMessage {
+    let setting =
+        |name: &str| matches.is_present(name) || metadata["bump"][name].as_bool() == Some(true);
+    CommitMessage {
+        trailers: setting("trailers"),
+        skip_ci: setting("skip-ci"),
+    }
+}
+
+/// Reads the `replacements` tables from `[package.metadata.bump]`, resolving each `file`
+/// against the manifest directory.
+fn replacements(metadata: &Value, manifest_dir: &Path) -> Vec<Replacement> {
+    let settings = match metadata["bump"]["replacements"].as_array() {
+        Some(settings) => settings,
+        None => return Vec::new(),
+    };
+    settings
+        .iter()
+        .map(|setting| {
+            let (file, pattern) = match (setting["file"].as_str(), setting["pattern"].as_str()) {
+                (Some(file), Some(pattern)) => (file, pattern),
+                _ => panic!("Each of the bump replacements needs a `file` and a `pattern`."),
+            };
+            if !pattern.contains(sync::PLACEHOLDER) {
+                panic!(
+                    "The replacement pattern `{}` for {} has no `{}`.",
+                    pattern,
+                    file,
+                    sync::PLACEHOLDER
+                );
+            }
+            Replacement {
+                file: manifest_dir.join(file),
+                pattern: pattern.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// The strings in a metadata array, or `None` when the setting isn't an array.
+fn metadata_strings(setting: &Value) -> Option<Vec<String>> {
+    setting.as_array().map(|values| {
+        values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect()
+    })
+}
+
+fn read_metadata(matches: &ArgMatches) -> Metadata {
+    let mut metadata_cmd = MetadataCommand::new();
+    if let Some(path) = matches.value_of("manifest-path") {
+        metadata_cmd.manifest_path(path);
+    }
+    metadata_cmd.exec().expect("get cargo metadata")
 }
 
 impl Config {
     fn from_matches(matches: ArgMatches) -> Config {
-        let mod_type = ModifierType::from_str(matches.value_of("version").unwrap_or("patch"))
-            .expect("Invalid semver version, expected version or major, minor, patch");
-        let build_metadata = matches.value_of("build-metadata").map(parse_identifiers);
-        let pre_release = matches.value_of("pre-release").map(parse_identifiers);
-        let git_tag = matches.is_present("git-tag");
-        let mut metadata_cmd = MetadataCommand::new();
-        if let Some(path) = matches.value_of("manifest-path") {
-            metadata_cmd.manifest_path(path);
-        }
-        let metadata = metadata_cmd.exec().expect("get cargo metadata");
+        let build_metadata = matches
+            .value_of("build-metadata")
+            .map(expand_build_metadata)
+            .map(|build| {
+                parse_identifiers(&build, IdentifierKind::BuildMetadata)
+                    .unwrap_or_else(|err| panic!("{}", err))
+            });
+        let (pre_release, pre_release_tags) = match matches.value_of("pre-release") {
+            Some("branch") => (Some(vec![branch_identifier()]), Some(tagged_versions())),
+            pre_release => (
+                pre_release.map(|pre| {
+                    parse_identifiers(pre, IdentifierKind::PreRelease)
+                        .unwrap_or_else(|err| panic!("{}", err))
+                }),
+                None,
+            ),
+        };
+        let changelog = matches.is_present("changelog");
+        let changelog_template = matches.value_of("changelog-template").map(PathBuf::from);
+        let allow_empty_changelog = matches.is_present("allow-empty-changelog");
+        let notes_file = matches.value_of("notes-file").map(PathBuf::from);
+        let mut allow_downgrade = matches.is_present("allow-downgrade");
+        let print_next = matches.is_present("print-next");
+        let strict = matches.is_present("strict");
+        let metadata = read_metadata(&matches);
         if metadata.workspace_members.len() == 1 {
+            let package = &metadata[&metadata.workspace_members[0]];
+            let git = release(&matches, &package.metadata);
+            let set = ["set-major", "set-minor", "set-patch"]
+                .iter()
+                .map(|&name| {
+                    matches.value_of(name).map(|value| {
+                        value.parse().unwrap_or_else(|_| {
+                            panic!("--{} expects a number, got {}", name, value)
+                        })
+                    })
+                })
+                .collect::<Vec<Option<u64>>>();
+            let version = match matches.value_of("version") {
+                None if set.iter().any(Option::is_some) => "set",
+                version => version.unwrap_or("patch"),
+            };
+            let is_snapshot = version == "snapshot";
+            let mod_type = match version {
+                "set" => ModifierType::Set {
+                    major: set[0],
+                    minor: set[1],
+                    patch: set[2],
+                },
+                "auto" => ModifierType::Auto,
+                "snapshot" => {
+                    if git.is_some() {
+                        panic!("Snapshots are not releases, they can't be committed or tagged.");
+                    }
+                    // A snapshot of an unreleased version in Cargo.toml sorts before it.
+                    allow_downgrade = true;
+                    ModifierType::Replace(snapshot(&package.version))
+                }
+                version => ModifierType::from_str(version).expect(
+                    "Invalid semver version, expected version or major, minor, patch, \
+                     optionally with +N, :keep-pre or :keep-build, or breaking, feature, fix, \
+                     calver, auto, snapshot",
+                ),
+            };
+            let mod_type = match (mod_type, matches.is_present("graduate")) {
+                (ModifierType::Major, true) => ModifierType::Graduate,
+                (_, true) => panic!("--graduate can only be used with major."),
+                (mod_type, false) => mod_type,
+            };
+            let mod_type = match (mod_type, matches.value_of("calver-format")) {
+                (ModifierType::CalVer(_), Some(format)) => {
+                    ModifierType::CalVer(format.parse().unwrap_or_else(|err| panic!("{}", err)))
+                }
+                (_, Some(_)) => panic!("--calver-format can only be used with calver."),
+                (mod_type, None) => mod_type,
+            };
+            let lib_path = package
+                .targets
+                .iter()
+                .find(|target| target.kind.iter().any(|kind| kind == "lib"))
+                .map(|target| target.src_path.clone());
+            let manifest_dir = package.manifest_path.parent().expect("manifest directory");
+            let readme = package
+                .readme
+                .as_ref()
+                .map(|readme| manifest_dir.join(readme))
+                .or_else(|| Some(manifest_dir.join("README.md")))
+                .filter(|readme| readme.is_file());
+            let dirty = dirty_policy(&matches, &package.metadata);
+            let release_branches = metadata_strings(&package.metadata["bump"]["release-branches"])
+                .unwrap_or_else(|| {
+                    git::DEFAULT_RELEASE_BRANCHES
+                        .iter()
+                        .map(|branch| branch.to_string())
+                        .collect()
+                });
+            let replacements = replacements(&package.metadata, manifest_dir);
+            let push = if matches.is_present("push") {
+                Some(
+                    matches
+                        .value_of("push")
+                        .or_else(|| package.metadata["bump"]["push-remote"].as_str())
+                        .map_or_else(git::default_remote, String::from),
+                )
+            } else {
+                None
+            };
             Config {
                 version_modifier: VersionModifier {
                     mod_type,
                     build_metadata,
                     pre_release,
+                    pre_release_tags,
+                    allow_downgrade,
                 },
-                manifest: metadata[&metadata.workspace_members[0]]
-                    .manifest_path
-                    .clone(),
-                git_tag,
+                manifest: package.manifest_path.clone(),
+                crate_name: package.name.clone(),
+                lib_path,
+                readme,
+                git,
+                print_next,
+                snapshot: is_snapshot,
+                strict,
+                changelog,
+                changelog_template,
+                allow_empty_changelog,
+                notes_file,
+                dirty,
+                release_branches,
+                replacements,
+                push,
             }
         } else {
             panic!("Workspaces are not supported yet.");
@@ -104,25 +743,220 @@ impl Config {
     }
 }
 
-fn parse_identifiers(value: &str) -> Vec<Identifier> {
+/// Derives a development snapshot version from the last release tag, or from the version in
+/// Cargo.toml when nothing has been tagged yet.
+fn snapshot(current: &Version) -> Version {
+    let tag = git::latest_tag();
+    let release = match tag {
+        Some(ref tag) => Version::parse(tag.trim_start_matches('v'))
+            .unwrap_or_else(|_| panic!("The last tag {} is not a semver version.", tag)),
+        None => current.clone(),
+    };
+    let commits = git::commit_count(tag.as_deref());
+    let short_sha = git::short_head().expect("Snapshots need a git commit.");
+    version::snapshot_version(&release, commits, &short_sha)
+}
+
+/// Turns the current git branch into a pre-release identifier, so `feat/new-parser` becomes
+/// `feat-new-parser`.
+fn branch_identifier() -> Identifier {
+    let branch = git::current_branch()
+        .expect("--pre-release branch needs a checked out git branch, HEAD is detached.");
+    let identifier = sanitize_identifier(&branch);
+    if identifier.is_empty() {
+        panic!(
+            "The branch name {} has no characters usable in a pre-release.",
+            branch
+        );
+    }
+    Identifier::AlphaNumeric(identifier)
+}
+
+fn sanitize_identifier(value: &str) -> String {
+    let mut sanitized = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    let sanitized = sanitized.trim_end_matches('-');
+    // Purely numeric identifiers would be compared as numbers, and can't have leading zeros.
+    if !sanitized.is_empty() && sanitized.chars().all(|c| c.is_ascii_digit()) {
+        format!("branch-{}", sanitized)
+    } else {
+        sanitized.to_string()
+    }
+}
+
+fn tagged_versions() -> Vec<Version> {
+    git::tags()
+        .iter()
+        .filter_map(|tag| Version::parse(tag.trim_start_matches('v')).ok())
+        .collect()
+}
+
+fn expand_build_metadata(template: &str) -> String {
+    if !build_template::is_template(template) {
+        return template.to_string();
+    }
+    build_template::expand(template, &BuildContext::from_environment())
+        .unwrap_or_else(|err| panic!("{}", err))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum IdentifierKind {
+    PreRelease,
+    BuildMetadata,
+}
+
+impl fmt::Display for IdentifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            IdentifierKind::PreRelease => "pre-release",
+            IdentifierKind::BuildMetadata => "build metadata",
+        })
+    }
+}
+
+/// Parses dot-separated identifiers following the SemVer 2.0 grammar. Numeric pre-release
+/// identifiers can't have leading zeros, while build metadata keeps them as text.
+fn parse_identifiers(value: &str, kind: IdentifierKind) -> Result<Vec<Identifier>, String> {
     value
         .split('.')
-        .map(|identifier| {
-            if let Ok(i) = identifier.parse() {
-                Identifier::Numeric(i)
-            } else {
-                Identifier::AlphaNumeric(identifier.to_string())
+        .enumerate()
+        .map(|(index, identifier)| {
+            let invalid = |reason: String| {
+                format!(
+                    "Invalid {} `{}`: identifier {} {}",
+                    kind,
+                    value,
+                    index + 1,
+                    reason
+                )
+            };
+            if identifier.is_empty() {
+                return Err(invalid(String::from("is empty")));
+            }
+            if let Some(bad) = identifier
+                .chars()
+                .find(|&c| !(c.is_ascii_alphanumeric() || c == '-'))
+            {
+                return Err(invalid(format!(
+                    "`{}` contains `{}`, only [0-9A-Za-z-] are allowed",
+                    identifier, bad
+                )));
+            }
+            if !identifier.chars().all(|c| c.is_ascii_digit()) {
+                return Ok(Identifier::AlphaNumeric(identifier.to_string()));
+            }
+            let numeric = identifier
+                .parse()
+                .ok()
+                .filter(|_| !identifier.starts_with('0') || identifier == "0");
+            match (kind, numeric) {
+                (_, Some(number)) => Ok(Identifier::Numeric(number)),
+                (IdentifierKind::BuildMetadata, None) => {
+                    Ok(Identifier::AlphaNumeric(identifier.to_string()))
+                }
+                (IdentifierKind::PreRelease, None) if identifier.starts_with('0') => Err(invalid(
+                    format!("`{}` is numeric with a leading zero", identifier),
+                )),
+                (IdentifierKind::PreRelease, None) => {
+                    Err(invalid(format!("`{}` is too large", identifier)))
+                }
             }
         })
         .collect()
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ModifierType {
     Replace(Version),
     Major,
     Minor,
     Patch,
+    /// Moves a `0.y.z` crate to `1.0.0`.
+    Graduate,
+    /// Bumps whichever position is breaking under Cargo's rules for the current version.
+    Breaking,
+    /// Bumps whichever position signals new functionality for the current version.
+    Feature,
+    /// Bumps whichever position signals a compatible fix for the current version.
+    Fix,
+    /// Computes the next calendar version from today's date.
+    CalVer(CalVerFormat),
+    /// Picks the position from the Conventional Commits since the last tag, which `main`
+    /// resolves into one of the semantic modifiers before bumping.
+    Auto,
+    /// Increments `position` by `amount`, like `minor+2`, optionally carrying the current
+    /// pre-release or build metadata over, like `major:keep-pre`.
+    Step {
+        position: Position,
+        amount: u64,
+        keep_pre: bool,
+        keep_build: bool,
+    },
+    /// Sets the given positions and keeps the others, from `--set-major` and friends.
+    Set {
+        major: Option<u64>,
+        minor: Option<u64>,
+        patch: Option<u64>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Position {
+    Major,
+    Minor,
+    Patch,
+}
+
+/// Parses `<position>[+<amount>][:keep-pre][:keep-build]`, returning `None` when `input`
+/// doesn't start with a position.
+fn parse_step(input: &str) -> Option<Result<ModifierType, String>> {
+    let mut parts = input.split(':');
+    let head = parts.next()?;
+    let (name, amount) = match head.find('+') {
+        Some(plus) => (&head[..plus], Some(&head[plus + 1..])),
+        None => (head, None),
+    };
+    let position = match name {
+        "major" => Position::Major,
+        "minor" => Position::Minor,
+        "patch" => Position::Patch,
+        _ => return None,
+    };
+    let amount = match amount.map(str::parse) {
+        None => 1,
+        Some(Ok(amount)) if amount > 0 => amount,
+        Some(_) => {
+            return Some(Err(format!(
+                "`{}` should be followed by +N with N a positive number",
+                name
+            )))
+        }
+    };
+    let (mut keep_pre, mut keep_build) = (false, false);
+    for part in parts {
+        match part {
+            "keep-pre" if !keep_pre => keep_pre = true,
+            "keep-build" if !keep_build => keep_build = true,
+            _ => {
+                return Some(Err(format!(
+                    "Unexpected `:{}` in `{}`, expected :keep-pre or :keep-build",
+                    part, input
+                )))
+            }
+        }
+    }
+    Some(Ok(ModifierType::Step {
+        position,
+        amount,
+        keep_pre,
+        keep_build,
+    }))
 }
 
 impl FromStr for ModifierType {
@@ -132,7 +966,14 @@ impl FromStr for ModifierType {
             "major" => ModifierType::Major,
             "minor" => ModifierType::Minor,
             "patch" => ModifierType::Patch,
-            _ => ModifierType::Replace(Version::parse(input)?),
+            "breaking" => ModifierType::Breaking,
+            "feature" => ModifierType::Feature,
+            "fix" => ModifierType::Fix,
+            "calver" => ModifierType::CalVer(CalVerFormat::default()),
+            _ => match parse_step(input) {
+                Some(step) => step.map_err(SemVerError::ParseError)?,
+                None => ModifierType::Replace(Version::parse(input)?),
+            },
         })
     }
 }
@@ -142,6 +983,10 @@ pub struct VersionModifier {
     pub mod_type: ModifierType,
     pub build_metadata: Option<Vec<Identifier>>,
     pub pre_release: Option<Vec<Identifier>>,
+    /// When set, the pre-release gets a numeric suffix one past the highest one already tagged
+    /// with the same version and pre-release prefix.
+    pub pre_release_tags: Option<Vec<Version>>,
+    pub allow_downgrade: bool,
 }
 
 impl VersionModifier {
@@ -153,17 +998,22 @@ impl VersionModifier {
     ) -> Self {
         Self {
             mod_type,
-            build_metadata: build_metadata.map(parse_identifiers),
-            pre_release: pre_release.map(parse_identifiers),
+            build_metadata: build_metadata
+                .map(|build| parse_identifiers(build, IdentifierKind::BuildMetadata).unwrap()),
+            pre_release: pre_release
+                .map(|pre| parse_identifiers(pre, IdentifierKind::PreRelease).unwrap()),
+            pre_release_tags: None,
+            allow_downgrade: false,
         }
     }
 
-    #[allow(unused)]
     pub fn from_mod_type(mod_type: ModifierType) -> Self {
         Self {
             mod_type,
             build_metadata: None,
             pre_release: None,
+            pre_release_tags: None,
+            allow_downgrade: false,
         }
     }
 }
@@ -196,6 +1046,110 @@ mod tests {
         test_config(input, VersionModifier::from_mod_type(ModifierType::Minor))
     }
 
+    #[test]
+    fn version_arg_breaking() {
+        let input = vec!["cargo-bump", "bump", "breaking"];
+        test_config(
+            input,
+            VersionModifier::from_mod_type(ModifierType::Breaking),
+        )
+    }
+
+    #[test]
+    fn parses_steps() {
+        let step = |position, amount, keep_pre, keep_build| ModifierType::Step {
+            position,
+            amount,
+            keep_pre,
+            keep_build,
+        };
+        let cases = vec![
+            ("minor+2", step(Position::Minor, 2, false, false)),
+            ("major:keep-pre", step(Position::Major, 1, true, false)),
+            ("patch:keep-build", step(Position::Patch, 1, false, true)),
+            (
+                "major+3:keep-build:keep-pre",
+                step(Position::Major, 3, true, true),
+            ),
+            ("minor", ModifierType::Minor),
+            (
+                "1.2.3+build",
+                ModifierType::Replace(Version::parse("1.2.3+build").unwrap()),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                input.parse::<ModifierType>().unwrap(),
+                expected,
+                "{}",
+                input
+            );
+        }
+        for input in &[
+            "minor+",
+            "minor+0",
+            "minor+x",
+            "major:keep",
+            "patch:keep-pre:keep-pre",
+        ] {
+            assert!(input.parse::<ModifierType>().is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn push_leaves_the_version_alone() {
+        let config = |input: Vec<&str>| {
+            Config::from_matches(build_cli_parser().get_matches_from_safe(input).unwrap())
+        };
+        let pushed = config(vec!["cargo-bump", "bump", "--git-tag", "--push", "minor"]);
+        assert_eq!(pushed.version_modifier.mod_type, ModifierType::Minor);
+        assert_eq!(pushed.push, Some(git::default_remote()));
+        let pushed = config(vec![
+            "cargo-bump",
+            "bump",
+            "--git-tag",
+            "--push=up",
+            "minor",
+        ]);
+        assert_eq!(pushed.version_modifier.mod_type, ModifierType::Minor);
+        assert_eq!(pushed.push.as_deref(), Some("up"));
+    }
+
+    #[test]
+    fn version_set_minor() {
+        let input = vec!["cargo-bump", "bump", "--set-minor", "7"];
+        let mod_type = ModifierType::Set {
+            major: None,
+            minor: Some(7),
+            patch: None,
+        };
+        test_config(input, VersionModifier::from_mod_type(mod_type))
+    }
+
+    #[test]
+    fn version_arg_major_graduate() {
+        let input = vec!["cargo-bump", "bump", "major", "--graduate"];
+        test_config(
+            input,
+            VersionModifier::from_mod_type(ModifierType::Graduate),
+        )
+    }
+
+    #[test]
+    fn version_arg_calver_format() {
+        let input = vec![
+            "cargo-bump",
+            "bump",
+            "calver",
+            "--calver-format",
+            "YY.WW.MICRO",
+        ];
+        test_config(
+            input,
+            VersionModifier::from_mod_type(ModifierType::CalVer("YY.WW.MICRO".parse().unwrap())),
+        )
+    }
+
     #[test]
     fn version_arg_string_good() {
         let input = vec!["cargo-bump", "bump", "1.2.3"];
@@ -212,6 +1166,8 @@ mod tests {
             mod_type: ModifierType::Major,
             build_metadata: Some(vec![Identifier::Numeric(1999)]),
             pre_release: None,
+            pre_release_tags: None,
+            allow_downgrade: false,
         };
         test_config(input, version_mod);
     }
@@ -223,6 +1179,74 @@ mod tests {
             mod_type: ModifierType::Replace(Version::parse("2.0.0").unwrap()),
             build_metadata: None,
             pre_release: Some(vec![Identifier::AlphaNumeric(String::from("beta"))]),
+            pre_release_tags: None,
+            allow_downgrade: false,
+        };
+        test_config(input, version_mod);
+    }
+
+    #[test]
+    fn validates_identifiers() {
+        use super::IdentifierKind::{BuildMetadata, PreRelease};
+        let alpha = |value: &str| Identifier::AlphaNumeric(value.to_string());
+        let valid = vec![
+            ("beta", PreRelease, vec![alpha("beta")]),
+            (
+                "rc.1",
+                PreRelease,
+                vec![alpha("rc"), Identifier::Numeric(1)],
+            ),
+            ("0", PreRelease, vec![Identifier::Numeric(0)]),
+            ("0a-1", PreRelease, vec![alpha("0a-1")]),
+            ("--", PreRelease, vec![alpha("--")]),
+            ("1999", BuildMetadata, vec![Identifier::Numeric(1999)]),
+            ("001.sha", BuildMetadata, vec![alpha("001"), alpha("sha")]),
+            (
+                "99999999999999999999",
+                BuildMetadata,
+                vec![alpha("99999999999999999999")],
+            ),
+        ];
+        for (value, kind, expected) in valid {
+            assert_eq!(parse_identifiers(value, kind), Ok(expected), "{}", value);
+        }
+        let invalid = vec![
+            ("", PreRelease, "identifier 1 is empty"),
+            ("a..b", PreRelease, "identifier 2 is empty"),
+            ("rc.", BuildMetadata, "identifier 2 is empty"),
+            ("rc_1", PreRelease, "`rc_1` contains `_`"),
+            ("a.b+c", BuildMetadata, "identifier 2 `b+c` contains `+`"),
+            ("caf\u{e9}", PreRelease, "contains `\u{e9}`"),
+            (
+                "rc.01",
+                PreRelease,
+                "identifier 2 `01` is numeric with a leading zero",
+            ),
+            ("99999999999999999999", PreRelease, "is too large"),
+        ];
+        for (value, kind, message) in invalid {
+            let err = parse_identifiers(value, kind).unwrap_err();
+            assert!(err.contains(message), "{}: {}", value, err);
+        }
+    }
+
+    #[test]
+    fn sanitizes_branch_names() {
+        assert_eq!(sanitize_identifier("feat/new-parser"), "feat-new-parser");
+        assert_eq!(sanitize_identifier("Fix__Bug #12"), "Fix-Bug-12");
+        assert_eq!(sanitize_identifier("/release/"), "release");
+        assert_eq!(sanitize_identifier("1234"), "branch-1234");
+        assert_eq!(sanitize_identifier("ü/ß"), "");
+    }
+
+    #[test]
+    fn version_allow_downgrade() {
+        let input = vec!["cargo-bump", "bump", "0.1.0", "--allow-downgrade"];
+        let version_mod = VersionModifier {
+            allow_downgrade: true,
+            ..VersionModifier::from_mod_type(ModifierType::Replace(
+                Version::parse("0.1.0").unwrap(),
+            ))
         };
         test_config(input, version_mod);
     }
@@ -0,0 +1,158 @@
+use git::Commit;
+use version::Change;
+
+/// A commit whose subject follows the Conventional Commits format,
+/// `type(scope)!: description`.
+#[derive(Debug, PartialEq)]
+pub struct ConventionalCommit<'a> {
+    pub commit: &'a Commit,
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub description: &'a str,
+    pub breaking: bool,
+    pub breaking_note: Option<&'a str>,
+}
+
+impl<'a> ConventionalCommit<'a> {
+    pub fn parse(commit: &'a Commit) -> Option<ConventionalCommit<'a>> {
+        let colon = commit.subject.find(": ")?;
+        let (header, description) = (&commit.subject[..colon], commit.subject[colon + 2..].trim());
+        let (header, bang) = match header.strip_suffix('!') {
+            Some(header) => (header, true),
+            None => (header, false),
+        };
+        let (kind, scope) = match header.find('(') {
+            Some(open) => {
+                let scope = header[open + 1..].strip_suffix(')')?;
+                (&header[..open], Some(scope))
+            }
+            None => (header, None),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let breaking_note = commit.body.lines().find_map(|line| {
+            line.strip_prefix("BREAKING CHANGE:")
+                .or_else(|| line.strip_prefix("BREAKING-CHANGE:"))
+                .map(str::trim)
+        });
+        Some(ConventionalCommit {
+            commit,
+            kind,
+            scope,
+            description,
+            breaking: bang || breaking_note.is_some(),
+            breaking_note,
+        })
+    }
+
+    pub fn short_hash(&self) -> &'a str {
+        &self.commit.hash[..self.commit.hash.len().min(7)]
+    }
+}
+
+/// Infers the kind of release `commits` add up to, along with the commits that drove the
+/// decision. Breaking changes win over features, and anything else is a fix.
+pub fn infer_change(commits: &[Commit]) -> (Change, Vec<ConventionalCommit<'_>>) {
+    let parsed: Vec<ConventionalCommit> = commits
+        .iter()
+        .filter_map(ConventionalCommit::parse)
+        .collect();
+    let change = parsed
+        .iter()
+        .map(|commit| {
+            if commit.breaking {
+                Change::Breaking
+            } else if commit.kind == "feat" {
+                Change::Feature
+            } else {
+                Change::Fix
+            }
+        })
+        .fold(
+            Change::Fix,
+            |highest, change| {
+                if change > highest {
+                    change
+                } else {
+                    highest
+                }
+            },
+        );
+    let reasons = parsed
+        .into_iter()
+        .filter(|commit| match change {
+            Change::Breaking => commit.breaking,
+            Change::Feature => commit.kind == "feat",
+            Change::Fix => true,
+        })
+        .collect();
+    (change, reasons)
+}
+
+#[cfg(test)]
+pub fn commit(subject: &str, body: &str) -> Commit {
+    Commit {
+        hash: String::from("3f2a9c1d2e4b"),
+        subject: subject.to_string(),
+        body: body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_type_scope_and_description() {
+        let input = commit("feat(parser): add arrays", "");
+        let parsed = ConventionalCommit::parse(&input).unwrap();
+        assert_eq!(parsed.kind, "feat");
+        assert_eq!(parsed.scope, Some("parser"));
+        assert_eq!(parsed.description, "add arrays");
+        assert!(!parsed.breaking);
+        assert_eq!(parsed.short_hash(), "3f2a9c1");
+    }
+
+    #[test]
+    fn detects_breaking_changes() {
+        let input = commit("refactor!: drop old api", "");
+        assert!(ConventionalCommit::parse(&input).unwrap().breaking);
+
+        let input = commit(
+            "fix: rename flag",
+            "Some text.\n\nBREAKING CHANGE: `-x` is now `-y`",
+        );
+        let parsed = ConventionalCommit::parse(&input).unwrap();
+        assert!(parsed.breaking);
+        assert_eq!(parsed.breaking_note, Some("`-x` is now `-y`"));
+    }
+
+    #[test]
+    fn infers_highest_change() {
+        let commits = vec![commit("fix: a", ""), commit("docs: b", "")];
+        let (change, reasons) = infer_change(&commits);
+        assert_eq!(change, Change::Fix);
+        assert_eq!(reasons.len(), 2);
+
+        let commits = vec![commit("fix: a", ""), commit("feat: b", "")];
+        let (change, reasons) = infer_change(&commits);
+        assert_eq!(change, Change::Feature);
+        assert_eq!(reasons[0].description, "b");
+
+        let commits = vec![commit("feat: a", ""), commit("fix(x)!: b", "")];
+        let (change, reasons) = infer_change(&commits);
+        assert_eq!(change, Change::Breaking);
+        assert_eq!(reasons.len(), 1);
+        assert_eq!(reasons[0].description, "b");
+
+        assert_eq!(infer_change(&[]).0, Change::Fix);
+    }
+
+    #[test]
+    fn rejects_free_form_subjects() {
+        assert!(ConventionalCommit::parse(&commit("Bump version", "")).is_none());
+        assert!(ConventionalCommit::parse(&commit("Merge branch 'a': b", "")).is_none());
+        assert!(ConventionalCommit::parse(&commit("feat(oops: b", "")).is_none());
+    }
+}
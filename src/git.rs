@@ -1,30 +1,942 @@
+use glob;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn git_check() {
+/// Which uncommitted changes may be left out of the release commit.
+#[derive(Debug, Default, PartialEq)]
+pub struct DirtyPolicy {
+    pub allow_dirty: bool,
+    pub allow_untracked: bool,
+    /// Globs of paths, relative to the repository root, whose changes never block a release.
+    pub ignore: Vec<String>,
+}
+
+impl DirtyPolicy {
+    /// The changes in `status` that block a release.
+    pub fn blocking<'a>(&self, status: &'a [StatusEntry]) -> Vec<&'a StatusEntry> {
+        if self.allow_dirty {
+            return Vec::new();
+        }
+        status
+            .iter()
+            .filter(|entry| !(self.allow_untracked && entry.is_untracked()))
+            .filter(|entry| {
+                !self
+                    .ignore
+                    .iter()
+                    .any(|glob| glob::matches(glob, &entry.path))
+            })
+            .collect()
+    }
+}
+
+/// The branches releases are made from when `release-branches` isn't configured.
+pub const DEFAULT_RELEASE_BRANCHES: [&str; 3] = ["main", "master", "release/*"];
+
+/// Refuses to release from a working directory with changes `policy` doesn't allow and, when
+/// `release` tags, from a detached HEAD, a branch that doesn't match one of the
+/// `release_branches` globs or one that is behind its upstream. Release commits alone can be
+/// made on any branch, such as for a pull request.
+pub fn git_check(policy: &DirtyPolicy, release_branches: &[String], release: &Release) {
+    git_check_in(Path::new("."), policy, release_branches, release)
+}
+
+fn git_check_in(dir: &Path, policy: &DirtyPolicy, release_branches: &[String], release: &Release) {
+    if toplevel_in(dir).is_none() {
+        panic!(
+            "Recording the release needs git and a git repository. Please install git or run \
+             this from a repository and try again."
+        );
+    }
+    if release.tag != TagMode::Skip {
+        let problems = branch_problems(dir, release_branches);
+        if !problems.is_empty() {
+            panic!(
+                "Refusing to release from here:\n  {}",
+                problems.join("\n  ")
+            );
+        }
+    }
+
+    let status = status_in(dir);
+    let blocking = policy.blocking(&status);
+    if !blocking.is_empty() {
+        let paths: Vec<String> = blocking
+            .iter()
+            .map(|entry| format!("  {} {}", entry.code, entry.path))
+            .collect();
+        panic!(
+            "Working directory is not clean. Please commit changes before trying to update the \
+             version, or pass --allow-dirty. These paths block the release:\n{}",
+            paths.join("\n")
+        );
+    }
+}
+
+/// Signing of the release commit and tag, with `key` overriding git's `user.signingkey`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Signing {
+    pub key: Option<String>,
+}
+
+/// Why git can't sign with `signing`, checked before any file is written so a missing key
+/// doesn't leave a half finished release behind.
+pub fn signing_problem(signing: &Signing) -> Option<String> {
+    signing_problem_in(Path::new("."), signing)
+}
+
+fn signing_problem_in(dir: &Path, signing: &Signing) -> Option<String> {
+    let format = read_in(dir, &["config", "gpg.format"]).unwrap_or_else(|| String::from("openpgp"));
+    let key = signing
+        .key
+        .clone()
+        .or_else(|| read_in(dir, &["config", "user.signingkey"]));
+    match format.as_str() {
+        "ssh" => {
+            let key = match key {
+                Some(key) => key,
+                None => {
+                    return Some(String::from(
+                        "gpg.format is ssh but no signing key is set, set user.signingkey or \
+                         signing-key in [package.metadata.bump]",
+                    ))
+                }
+            };
+            if key.starts_with("key::") || key.starts_with("ssh-") {
+                return None;
+            }
+            let path = match (key.strip_prefix("~/"), env::var_os("HOME")) {
+                (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+                _ => dir.join(&key),
+            };
+            if path.is_file() {
+                None
+            } else {
+                Some(format!("the SSH signing key {} does not exist", key))
+            }
+        }
+        "openpgp" => {
+            let program =
+                read_in(dir, &["config", "gpg.program"]).unwrap_or_else(|| String::from("gpg"));
+            let id = match key.or_else(|| read_in(dir, &["config", "user.email"])) {
+                Some(id) => id,
+                None => {
+                    return Some(String::from(
+                        "no signing key is set and git has no user.email to find one by",
+                    ))
+                }
+            };
+            match Command::new(&program)
+                .args(["--batch", "--list-secret-keys", &id])
+                .output()
+            {
+                Ok(ref output) if output.status.success() => None,
+                Ok(_) => Some(format!("{} has no secret key for {}", program, id)),
+                Err(_) => Some(format!(
+                    "{} is needed to sign but could not be run",
+                    program
+                )),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether and how the bump is committed.
+#[derive(Debug, PartialEq)]
+pub enum CommitMode {
+    /// Leave HEAD alone, which only makes sense when it already has the new version.
+    Skip,
+    New,
+    /// Fold the bump into the last commit, keeping its message.
+    Amend,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TagMode {
+    Skip,
+    Annotated,
+    Lightweight,
+}
+
+/// How a release is recorded in git.
+#[derive(Debug, PartialEq)]
+pub struct Release {
+    pub commit: CommitMode,
+    pub tag: TagMode,
+    pub sign: Option<Signing>,
+    /// Ignored with `CommitMode::Amend`, which keeps the last commit's message.
+    pub message: CommitMessage,
+}
+
+/// A crate a release commit bumps.
+#[derive(Debug, PartialEq)]
+pub struct Released {
+    pub name: String,
+    pub version: String,
+    pub previous: Option<String>,
+    /// The release notes to put in the body of an annotated tag.
+    pub notes: Option<String>,
+}
+
+/// What goes into the release commit message besides the version.
+#[derive(Debug, Default, PartialEq)]
+pub struct CommitMessage {
+    /// Add `Release-Crate`, `Release-Version` and `Previous-Version` trailers, grouped per
+    /// crate.
+    pub trailers: bool,
+    /// Mark the commit with `[skip ci]`.
+    pub skip_ci: bool,
+}
+
+impl CommitMessage {
+    /// The message for a commit releasing `released`: the version alone for a single crate, or
+    /// each crate and its version for several.
+    pub fn render(&self, released: &[Released]) -> String {
+        let mut message = match released {
+            [single] => single.version.clone(),
+            _ => format!(
+                "Release {}",
+                released
+                    .iter()
+                    .map(|krate| format!("{} {}", krate.name, krate.version))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        };
+        if self.skip_ci {
+            message.push_str(" [skip ci]");
+        }
+        if self.trailers {
+            // The groups share one paragraph, as git only reads trailers from the last one.
+            message.push('\n');
+            for krate in released {
+                message.push_str(&format!("\nRelease-Crate: {}", krate.name));
+                message.push_str(&format!("\nRelease-Version: {}", krate.version));
+                if let Some(ref previous) = krate.previous {
+                    message.push_str(&format!("\nPrevious-Version: {}", previous));
+                }
+            }
+        }
+        message
+    }
+}
+
+impl Release {
+    /// Commits the `paths` the bump changed and nothing else, so changes allowed by a
+    /// `DirtyPolicy` stay uncommitted, then tags the result. A single crate is tagged with its
+    /// version and several with `<name>-<version>` each. Returns the tags created.
+    pub fn record(&self, released: &[Released], paths: &[PathBuf]) -> Vec<String> {
+        self.record_in(Path::new("."), released, paths)
+    }
+
+    fn record_in(&self, dir: &Path, released: &[Released], paths: &[PathBuf]) -> Vec<String> {
+        if self.commit != CommitMode::Skip {
+            let mut add = Command::new("git");
+            add.args(["add", "--"]).args(paths);
+            run(dir, &mut add, "adding the bumped files to the git index");
+
+            let mut commit = Command::new("git");
+            commit.arg("commit");
+            if let Some(ref signing) = self.sign {
+                commit.arg(format!("-S{}", signing.key.as_deref().unwrap_or("")));
+            }
+            match self.commit {
+                CommitMode::Amend => commit.args(["--amend", "--no-edit"]),
+                _ => commit.args(["-m", &self.message.render(released)]),
+            };
+            commit.arg("--").args(paths);
+            run(dir, &mut commit, "committing the new version");
+        }
+
+        if self.tag == TagMode::Skip {
+            return Vec::new();
+        }
+        let mut tags = Vec::new();
+        for krate in released {
+            let name = match released {
+                [_] => krate.version.clone(),
+                _ => format!("{}-{}", krate.name, krate.version),
+            };
+            let mut tag = Command::new("git");
+            tag.arg("tag");
+            match (&self.tag, &self.sign) {
+                (TagMode::Annotated, Some(Signing { key: Some(key) })) => tag.args(["-u", key]),
+                (TagMode::Annotated, Some(Signing { key: None })) => tag.arg("-s"),
+                (TagMode::Annotated, None) => tag.arg("-a"),
+                _ => &mut tag,
+            };
+            if self.tag == TagMode::Annotated {
+                let message = match krate.notes {
+                    Some(ref notes) => format!("{}\n\n{}", name, notes),
+                    None => name.clone(),
+                };
+                // Markdown headings in the notes would otherwise be stripped as comments.
+                tag.args(["--cleanup=whitespace", "-m", &message]);
+            }
+            tag.arg(&name);
+            run(dir, &mut tag, "creating the git tag");
+            tags.push(name);
+        }
+        tags
+    }
+}
+
+/// Runs a git `command` in `dir`, panicking with git's error output when `what` failed.
+fn run(dir: &Path, command: &mut Command, what: &str) {
+    let output = command
+        .current_dir(dir)
+        .output()
+        .expect("This tool requires git. Please install git and try again.");
+    if !output.status.success() {
+        panic!(
+            "Something went wrong {}: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+}
+
+/// The remote to push releases to when none is given: the current branch's upstream remote,
+/// or `origin`.
+pub fn default_remote() -> String {
+    current_branch()
+        .and_then(|branch| read(&["config", &format!("branch.{}.remote", branch)]))
+        .unwrap_or_else(|| String::from("origin"))
+}
+
+pub fn remote_exists(remote: &str) -> bool {
+    read(&["remote", "get-url", remote]).is_some()
+}
+
+/// Pushes the current branch and `tags` to `remote` in one atomic push, so either the release
+/// commit and its tags all arrive or nothing changes on the remote.
+pub fn push(remote: &str, tags: &[String]) -> Result<(), String> {
+    push_from(Path::new("."), remote, tags)
+}
+
+fn push_from(dir: &Path, remote: &str, tags: &[String]) -> Result<(), String> {
+    let branch = read_in(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"])
+        .ok_or_else(|| String::from("HEAD is detached, there is no branch to push"))?;
+    let mut refs = vec![format!("refs/heads/{}", branch)];
+    refs.extend(tags.iter().map(|tag| format!("refs/tags/{}", tag)));
     let output = Command::new("git")
-        .args(&["status", "--porcelain"])
+        .current_dir(dir)
+        .args(["push", "--atomic", remote])
+        .args(&refs)
         .output()
         .expect("This tool requires git. Please install git and try again.");
-    if !output.stdout.is_empty() {
-        panic!("Working directory is not clean. Please commit changes before trying to update the version.");
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Could not push to {}: {}\nThe release commit and tags are kept locally, push them \
+             with `git push --atomic {} {}`.",
+            remote,
+            String::from_utf8_lossy(&output.stderr).trim(),
+            remote,
+            refs.join(" ")
+        ))
     }
 }
 
-pub fn git_tag(version: &str) {
-    Command::new("git")
-        .args(&["tag", "-am", version, version])
-        .status()
-        .expect("Something went wrong when creating a git tag.");
+/// Why the repository in `dir` can't be released from its current branch. The upstream is
+/// fetched first, falling back to what is known locally when that fails.
+fn branch_problems(dir: &Path, release_branches: &[String]) -> Vec<String> {
+    let branch = match read_in(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+        Some(branch) => branch,
+        None => return vec![String::from("HEAD is detached, check out a release branch")],
+    };
+    let mut problems = Vec::new();
+    if !release_branches
+        .iter()
+        .any(|pattern| glob::matches_whole(pattern, &branch))
+    {
+        problems.push(format!(
+            "`{}` is not a release branch, expected one of: {}",
+            branch,
+            release_branches.join(", ")
+        ));
+    }
+    if let Some(upstream) = read_in(dir, &["rev-parse", "--abbrev-ref", "@{upstream}"]) {
+        if read_in(dir, &["fetch", "--quiet"]).is_none() {
+            eprintln!(
+                "warning: could not fetch {}, comparing with the last fetched state",
+                upstream
+            );
+        }
+        let behind: u64 = read_in(dir, &["rev-list", "--count", "HEAD..@{upstream}"])
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0);
+        if behind > 0 {
+            problems.push(format!(
+                "`{}` is {} commit(s) behind {}, pull first",
+                branch, behind, upstream
+            ));
+        }
+    }
+    problems
 }
 
-pub fn git_commit(version: &str) {
-    Command::new("git")
-        .args(&["commit", "-am", version])
-        .status()
-        .expect("Something went wrong trying to commit the new version.");
+/// Runs git with `args`, returning its trimmed output if it succeeded.
+fn read(args: &[&str]) -> Option<String> {
+    read_in(Path::new("."), args)
 }
 
-pub fn git_commit_and_tag(version: &str) {
-    git_commit(version);
-    git_tag(version);
+/// Runs git with `args` in `dir`, returning its trimmed output if it succeeded. Returns
+/// `None` as well when git isn't installed, so plain bumps work without it.
+fn read_in(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .trim_end()
+                .to_string(),
+        )
+    } else {
+        None
+    }
+}
+
+/// A line of `git status`: the two letter status code and the path it applies to.
+#[derive(Debug, PartialEq)]
+pub struct StatusEntry {
+    pub code: String,
+    pub path: String,
+}
+
+impl StatusEntry {
+    pub fn is_untracked(&self) -> bool {
+        self.code == "??"
+    }
+}
+
+fn status_in(dir: &Path) -> Vec<StatusEntry> {
+    read_in(dir, &["status", "--porcelain", "-z"])
+        .map(|output| parse_status(&output))
+        .unwrap_or_default()
+}
+
+fn parse_status(output: &str) -> Vec<StatusEntry> {
+    let mut entries = Vec::new();
+    let mut records = output.split('\0').filter(|record| !record.is_empty());
+    while let Some(record) = records.next() {
+        let code = &record[..2];
+        if code.starts_with('R') || code.starts_with('C') {
+            // Renames and copies are followed by the path they came from.
+            records.next();
+        }
+        entries.push(StatusEntry {
+            code: code.to_string(),
+            path: record[3..].to_string(),
+        });
+    }
+    entries
+}
+
+pub fn is_dirty() -> bool {
+    read(&["status", "--porcelain"]).is_some_and(|status| !status.is_empty())
+}
+
+pub fn is_tracked(path: &Path) -> bool {
+    read(&["ls-files", "--error-unmatch", &path.to_string_lossy()]).is_some()
+}
+
+pub fn latest_tag() -> Option<String> {
+    latest_tag_in(Path::new("."))
+}
+
+pub fn latest_tag_in(dir: &Path) -> Option<String> {
+    read_in(dir, &["describe", "--tags", "--abbrev=0"])
+}
+
+/// Counts the commits reachable from HEAD but not from `since`.
+pub fn commit_count(since: Option<&str>) -> u64 {
+    let range = match since {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => String::from("HEAD"),
+    };
+    read(&["rev-list", "--count", &range])
+        .and_then(|count| count.parse().ok())
+        .unwrap_or_else(|| panic!("Could not count the commits in {}", range))
+}
+
+/// The name of the checked out branch, or `None` when HEAD is detached.
+pub fn current_branch() -> Option<String> {
+    read(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+}
+
+pub fn tags() -> Vec<String> {
+    read(&["tag", "--list"])
+        .map(|tags| tags.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// The root of the working tree.
+pub fn toplevel() -> Option<PathBuf> {
+    toplevel_in(Path::new("."))
+}
+
+fn toplevel_in(dir: &Path) -> Option<PathBuf> {
+    let root = PathBuf::from(read_in(dir, &["rev-parse", "--show-toplevel"])?);
+    Some(root.canonicalize().unwrap_or(root))
+}
+
+/// The contents of `path` as of `rev`, or `None` when it didn't exist there.
+pub fn show(rev: &str, path: &Path) -> Option<String> {
+    show_in(Path::new("."), rev, path)
+}
+
+/// Like `show`, for the repository containing `dir`.
+pub fn show_in(dir: &Path, rev: &str, path: &Path) -> Option<String> {
+    let root = toplevel_in(dir)?;
+    let relative = path.strip_prefix(&root).ok()?;
+    read_in(dir, &["show", &format!("{}:{}", rev, relative.display())])
+}
+
+/// The files changed in the working tree since it forked from `base`, including untracked
+/// ones, as absolute paths.
+pub fn changed_files(base: &str) -> Vec<PathBuf> {
+    let root = toplevel().expect("This is not a git repository.");
+    let fork = read(&["merge-base", base, "HEAD"])
+        .unwrap_or_else(|| panic!("Could not find where HEAD forked from {}.", base));
+    let changed = read(&["diff", "--name-only", &fork]).unwrap_or_default();
+    let untracked =
+        read(&["ls-files", "--others", "--exclude-standard", "--full-name"]).unwrap_or_default();
+    changed
+        .lines()
+        .chain(untracked.lines())
+        .map(|path| root.join(path))
+        .collect()
+}
+
+/// The abbreviated hash of HEAD.
+pub fn short_head() -> Option<String> {
+    read(&["rev-parse", "--short", "HEAD"])
+}
+
+/// The commit date of HEAD as `YYYYMMDD`.
+pub fn head_date() -> Option<String> {
+    read(&["log", "-1", "--format=%cd", "--date=format:%Y%m%d"])
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Commit {
+    pub hash: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Lists the commits reachable from HEAD but not from `since`, newest first.
+pub fn commits_since(since: Option<&str>) -> Vec<Commit> {
+    let range = match since {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => String::from("HEAD"),
+    };
+    let output = Command::new("git")
+        .args(["log", "--format=%H%x1f%s%x1f%b%x1e", &range])
+        .output()
+        .expect("This tool requires git. Please install git and try again.");
+    if !output.status.success() {
+        panic!(
+            "Could not list commits in {}: {}",
+            range,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+            Some(Commit {
+                hash: fields.next().filter(|hash| !hash.is_empty())?.to_string(),
+                subject: fields.next()?.to_string(),
+                body: fields.next().unwrap_or("").trim_end().to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scratch::{git, Scratch};
+    use std::fs;
+
+    fn release_branches() -> Vec<String> {
+        DEFAULT_RELEASE_BRANCHES
+            .iter()
+            .map(|branch| branch.to_string())
+            .collect()
+    }
+
+    fn entry(code: &str, path: &str) -> StatusEntry {
+        StatusEntry {
+            code: code.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_porcelain_status() {
+        assert_eq!(
+            parse_status(" M src/main.rs\0R  new.rs\0old.rs\0?? notes file.txt\0"),
+            vec![
+                entry(" M", "src/main.rs"),
+                entry("R ", "new.rs"),
+                entry("??", "notes file.txt"),
+            ]
+        );
+        assert_eq!(
+            parse_status("R  new.rs\0ab\0?? x.txt\0"),
+            vec![entry("R ", "new.rs"), entry("??", "x.txt")]
+        );
+    }
+
+    #[test]
+    fn applies_dirty_policy() {
+        let status = vec![
+            entry(" M", "src/main.rs"),
+            entry("??", "scratch.txt"),
+            entry(" M", "logs/run.log"),
+        ];
+        let paths = |policy: DirtyPolicy| -> Vec<String> {
+            policy
+                .blocking(&status)
+                .iter()
+                .map(|entry| entry.path.clone())
+                .collect()
+        };
+        assert_eq!(paths(DirtyPolicy::default()).len(), 3);
+        assert_eq!(
+            paths(DirtyPolicy {
+                allow_untracked: true,
+                ignore: vec![String::from("*.log")],
+                ..DirtyPolicy::default()
+            }),
+            vec!["src/main.rs"]
+        );
+        assert!(paths(DirtyPolicy {
+            allow_dirty: true,
+            ..DirtyPolicy::default()
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn checks_release_branch_against_upstream() {
+        let scratch = Scratch::new("branches");
+        git(&scratch.0, &["init", "--quiet", "--bare", "remote.git"]);
+        git(&scratch.0, &["clone", "--quiet", "remote.git", "local"]);
+        git(&scratch.0, &["clone", "--quiet", "remote.git", "other"]);
+        let local = scratch.0.join("local");
+        let other = scratch.0.join("other");
+
+        git(
+            &local,
+            &["commit", "--quiet", "--allow-empty", "-m", "initial"],
+        );
+        git(&local, &["push", "--quiet", "-u", "origin", "main"]);
+        assert!(branch_problems(&local, &release_branches()).is_empty());
+
+        git(&local, &["checkout", "--quiet", "-b", "feature/main"]);
+        assert_eq!(
+            branch_problems(&local, &release_branches()),
+            vec![
+                "`feature/main` is not a release branch, expected one of: main, master, release/*"
+            ]
+        );
+        git(&local, &["checkout", "--quiet", "-b", "release/1.0"]);
+        assert!(branch_problems(&local, &release_branches()).is_empty());
+
+        git(&local, &["checkout", "--quiet", "--detach", "main"]);
+        assert_eq!(
+            branch_problems(&local, &release_branches()),
+            vec!["HEAD is detached, check out a release branch"]
+        );
+
+        git(&local, &["checkout", "--quiet", "main"]);
+        git(&other, &["pull", "--quiet"]);
+        git(
+            &other,
+            &["commit", "--quiet", "--allow-empty", "-m", "elsewhere"],
+        );
+        git(&other, &["push", "--quiet"]);
+        assert_eq!(
+            branch_problems(&local, &release_branches()),
+            vec!["`main` is 1 commit(s) behind origin/main, pull first"]
+        );
+    }
+
+    #[test]
+    fn commits_on_any_branch_but_tags_on_release_branches() {
+        let scratch = Scratch::new("feature-commit");
+        let dir = &scratch.0;
+        git(dir, &["init", "--quiet"]);
+        git(
+            dir,
+            &["commit", "--quiet", "--allow-empty", "-m", "initial"],
+        );
+        git(dir, &["checkout", "--quiet", "-b", "feat/x"]);
+        git(dir, &["config", "user.name", "test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(dir, &["config", "commit.gpgsign", "false"]);
+        let release = |tag| Release {
+            commit: CommitMode::New,
+            tag,
+            sign: None,
+            message: CommitMessage::default(),
+        };
+        let released = vec![Released {
+            name: String::from("foo"),
+            version: String::from("0.2.0"),
+            previous: Some(String::from("0.1.0")),
+            notes: None,
+        }];
+        let paths = [dir.join("Cargo.toml")];
+        fs::write(&paths[0], "0.2.0").unwrap();
+
+        let policy = DirtyPolicy {
+            allow_dirty: true,
+            ..DirtyPolicy::default()
+        };
+        let tagged = std::panic::catch_unwind(|| {
+            git_check_in(
+                dir,
+                &policy,
+                &release_branches(),
+                &release(TagMode::Annotated),
+            )
+        });
+        assert!(tagged.is_err());
+        git_check_in(dir, &policy, &release_branches(), &release(TagMode::Skip));
+        release(TagMode::Skip).record_in(dir, &released, &paths);
+        assert_eq!(
+            read_in(dir, &["log", "--format=%s", "-1"]).unwrap(),
+            "0.2.0"
+        );
+        assert_eq!(
+            read_in(dir, &["symbolic-ref", "--short", "HEAD"]).unwrap(),
+            "feat/x"
+        );
+    }
+
+    #[test]
+    fn pushes_branch_and_release_tags() {
+        let scratch = Scratch::new("push");
+        git(&scratch.0, &["init", "--quiet", "--bare", "remote.git"]);
+        git(&scratch.0, &["clone", "--quiet", "remote.git", "local"]);
+        let local = scratch.0.join("local");
+        let remote = scratch.0.join("remote.git");
+        let remote_refs = || read_in(&remote, &["for-each-ref", "--format=%(refname)"]);
+
+        git(
+            &local,
+            &["commit", "--quiet", "--allow-empty", "-m", "0.1.0"],
+        );
+        git(&local, &["tag", "unrelated"]);
+        git(&local, &["tag", "-am", "0.1.0", "0.1.0"]);
+        push_from(&local, "origin", &[String::from("0.1.0")]).expect("push");
+        assert_eq!(remote_refs().unwrap(), "refs/heads/main\nrefs/tags/0.1.0");
+
+        let error = push_from(&local, "missing", &[String::from("0.1.0")]).unwrap_err();
+        assert!(error.contains("kept locally"), "{}", error);
+        assert!(read_in(&local, &["rev-parse", "--verify", "refs/tags/0.1.0"]).is_some());
+    }
+
+    #[test]
+    fn detects_and_uses_ssh_signing_keys() {
+        let scratch = Scratch::new("signing");
+        git(&scratch.0, &["init", "--quiet"]);
+        git(&scratch.0, &["config", "gpg.format", "ssh"]);
+        assert!(signing_problem_in(&scratch.0, &Signing::default())
+            .unwrap()
+            .contains("no signing key is set"));
+
+        let status = Command::new("ssh-keygen")
+            .current_dir(&scratch.0)
+            .args(["-q", "-t", "ed25519", "-N", "", "-f", "release_key"])
+            .status()
+            .expect("ssh-keygen");
+        assert!(status.success());
+        let signing = |key: &str| Signing {
+            key: Some(key.to_string()),
+        };
+        assert_eq!(
+            signing_problem_in(&scratch.0, &signing("release_key")),
+            None
+        );
+        assert_eq!(
+            signing_problem_in(&scratch.0, &signing("missing_key")),
+            Some(String::from(
+                "the SSH signing key missing_key does not exist"
+            ))
+        );
+
+        git(&scratch.0, &["config", "user.name", "test"]);
+        git(&scratch.0, &["config", "user.email", "test@example.com"]);
+        let paths = [scratch.0.join("Cargo.toml")];
+        let signed = |key: Option<&str>, version: &str| {
+            fs::write(&paths[0], version).expect("manifest");
+            Release {
+                commit: CommitMode::New,
+                tag: TagMode::Annotated,
+                sign: Some(Signing {
+                    key: key.map(String::from),
+                }),
+                message: CommitMessage::default(),
+            }
+            .record_in(
+                &scratch.0,
+                &[Released {
+                    name: String::from("foo"),
+                    version: version.to_string(),
+                    previous: None,
+                    notes: None,
+                }],
+                &paths,
+            );
+            for &(kind, object) in &[("commit", "HEAD"), ("tag", version)] {
+                assert!(
+                    read_in(&scratch.0, &["cat-file", kind, object])
+                        .unwrap()
+                        .contains("-----BEGIN SSH SIGNATURE-----"),
+                    "the {} is not signed",
+                    kind
+                );
+            }
+        };
+        // `-S<key>` and `-u <key>` with the key from the config.
+        signed(Some("release_key"), "0.1.0");
+
+        // `-S` and `-s` with git's own user.signingkey.
+        git(&scratch.0, &["config", "user.signingkey", "release_key"]);
+        assert_eq!(signing_problem_in(&scratch.0, &Signing::default()), None);
+        signed(None, "0.2.0");
+    }
+
+    #[test]
+    fn records_releases() {
+        let scratch = Scratch::new("record");
+        let dir = &scratch.0;
+        git(dir, &["init", "--quiet"]);
+        git(dir, &["config", "user.name", "test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+        git(
+            dir,
+            &["commit", "--quiet", "--allow-empty", "-m", "initial"],
+        );
+        let manifest = dir.join("Cargo.toml");
+        let bump = |version: &str| fs::write(&manifest, version).expect("manifest");
+        let paths = [manifest.clone()];
+        let release = |commit, tag| Release {
+            commit,
+            tag,
+            sign: None,
+            message: CommitMessage::default(),
+        };
+        let released = |version: &str| {
+            vec![Released {
+                name: String::from("foo"),
+                version: version.to_string(),
+                previous: None,
+                notes: None,
+            }]
+        };
+        let head = || read_in(dir, &["log", "--format=%s", "-1"]).unwrap();
+        let count = || read_in(dir, &["rev-list", "--count", "HEAD"]).unwrap();
+        let tag_type = |tag: &str| read_in(dir, &["cat-file", "-t", tag]).unwrap();
+
+        bump("0.1.0");
+        let mut first = released("0.1.0");
+        first[0].notes = Some(String::from("### Added\n\n- First release."));
+        let tags = release(CommitMode::New, TagMode::Annotated).record_in(dir, &first, &paths);
+        assert_eq!(tags, vec!["0.1.0"]);
+        assert_eq!(
+            read_in(dir, &["tag", "-l", "--format=%(contents)", "0.1.0"]).unwrap(),
+            "0.1.0\n\n### Added\n\n- First release."
+        );
+        assert_eq!(
+            (head(), count(), tag_type("0.1.0")),
+            ("0.1.0".into(), "2".into(), "tag".into())
+        );
+
+        bump("0.2.0");
+        let tags =
+            release(CommitMode::New, TagMode::Skip).record_in(dir, &released("0.2.0"), &paths);
+        assert!(tags.is_empty());
+        assert_eq!((head(), count()), ("0.2.0".into(), "3".into()));
+        release(CommitMode::Skip, TagMode::Lightweight).record_in(dir, &released("0.2.0"), &paths);
+        assert_eq!(tag_type("0.2.0"), "commit");
+
+        bump("0.2.1");
+        release(CommitMode::Amend, TagMode::Skip).record_in(dir, &released("0.2.1"), &paths);
+        assert_eq!((head(), count()), ("0.2.0".into(), "3".into()));
+        assert_eq!(read_in(dir, &["show", "HEAD:Cargo.toml"]).unwrap(), "0.2.1");
+
+        bump("0.3.0");
+        Release {
+            message: CommitMessage {
+                trailers: true,
+                skip_ci: false,
+            },
+            ..release(CommitMode::New, TagMode::Skip)
+        }
+        .record_in(dir, &released("0.3.0"), &paths);
+        assert_eq!(
+            read_in(
+                dir,
+                &[
+                    "log",
+                    "-1",
+                    "--format=%(trailers:key=Release-Version,valueonly)"
+                ]
+            )
+            .unwrap(),
+            "0.3.0"
+        );
+    }
+
+    #[test]
+    fn renders_commit_messages() {
+        let released = |name: &str, version: &str, previous: Option<&str>| Released {
+            name: name.to_string(),
+            version: version.to_string(),
+            previous: previous.map(String::from),
+            notes: None,
+        };
+        let single = [released("foo", "1.2.3", Some("1.2.2"))];
+        assert_eq!(CommitMessage::default().render(&single), "1.2.3");
+        let message = CommitMessage {
+            trailers: true,
+            skip_ci: true,
+        };
+        assert_eq!(
+            message.render(&single),
+            "1.2.3 [skip ci]\n\
+             \n\
+             Release-Crate: foo\n\
+             Release-Version: 1.2.3\n\
+             Previous-Version: 1.2.2"
+        );
+        let workspace = [
+            released("bar", "0.4.0", Some("0.3.1")),
+            released("baz", "0.1.0", None),
+        ];
+        assert_eq!(
+            message.render(&workspace),
+            "Release bar 0.4.0, baz 0.1.0 [skip ci]\n\
+             \n\
+             Release-Crate: bar\n\
+             Release-Version: 0.4.0\n\
+             Previous-Version: 0.3.1\n\
+             Release-Crate: baz\n\
+             Release-Version: 0.1.0"
+        );
+    }
 }
@@ -0,0 +1,72 @@
+/// Matches `path` against a glob `pattern`, where `*` matches within a path component, `**`
+/// matches across components and `?` matches one character. Patterns without a `/` match
+/// the file name in any directory, like in `.gitignore`.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches('/');
+    if pattern.contains('/') {
+        match_from(pattern.as_bytes(), path.as_bytes())
+    } else {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        match_from(pattern.as_bytes(), name.as_bytes())
+    }
+}
+
+/// Like `matches`, but the pattern always has to match all of `path`, so `main` doesn't match
+/// `feature/main`.
+pub fn matches_whole(pattern: &str, path: &str) -> bool {
+    match_from(pattern.as_bytes(), path.as_bytes())
+}
+
+fn match_from(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            // `**/` also matches no directories at all.
+            let rest = &pattern[2..];
+            if rest.first() == Some(&b'/') && match_from(&rest[1..], path) {
+                return true;
+            }
+            (0..=path.len()).any(|skip| match_from(rest, &path[skip..]))
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            (0..=path.len())
+                .take_while(|&skip| skip == 0 || path[skip - 1] != b'/')
+                .any(|skip| match_from(rest, &path[skip..]))
+        }
+        Some(b'?') => {
+            path.first().is_some_and(|&c| c != b'/') && match_from(&pattern[1..], &path[1..])
+        }
+        Some(&c) => path.first() == Some(&c) && match_from(&pattern[1..], &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        let cases = [
+            ("*.log", "debug.log", true),
+            ("*.log", "logs/debug.log", true),
+            ("*.log", "debug.log.old", false),
+            ("scratch/*", "scratch/notes.md", true),
+            ("scratch/*", "scratch/deep/notes.md", false),
+            ("scratch/**", "scratch/deep/notes.md", true),
+            ("**/notes.md", "notes.md", true),
+            ("**/notes.md", "a/b/notes.md", true),
+            ("/Cargo.lock", "Cargo.lock", true),
+            ("docs/?.md", "docs/a.md", true),
+            ("docs/?.md", "docs/ab.md", false),
+            ("src/*.rs", "src/main.rs", true),
+            ("src/*.rs", "tests/main.rs", false),
+        ];
+        for &(pattern, path, expected) in &cases {
+            assert_eq!(matches(pattern, path), expected, "{} {}", pattern, path);
+        }
+        assert!(matches_whole("release/*", "release/1.2"));
+        assert!(!matches_whole("main", "feature/main"));
+        assert!(matches_whole("**", "feature/main"));
+    }
+}
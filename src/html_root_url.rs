@@ -0,0 +1,102 @@
+use std::ops::Range;
+
+/// Finds the version segment of every `html_root_url = "https://docs.rs/<crate>/<version>"`
+/// in `source` that points at `crate_name`.
+fn version_spans(source: &str, crate_name: &str) -> Vec<Range<usize>> {
+    let prefixes = [
+        format!("https://docs.rs/{}/", crate_name),
+        format!("https://docs.rs/{}/", crate_name.replace('-', "_")),
+    ];
+    let mut spans = Vec::new();
+    for (start, key) in source.match_indices("html_root_url") {
+        let rest = &source[start + key.len()..];
+        let after_eq = match rest.trim_start().strip_prefix('=') {
+            Some(after_eq) => after_eq,
+            None => continue,
+        };
+        let url = match after_eq.trim_start().strip_prefix('"') {
+            Some(url) => url,
+            None => continue,
+        };
+        let url_start = source.len() - url.len();
+        let url = match url.find('"') {
+            Some(end) => &url[..end],
+            None => continue,
+        };
+        if let Some(prefix) = prefixes
+            .iter()
+            .find(|prefix| url.starts_with(prefix.as_str()))
+        {
+            let version = &url[prefix.len()..];
+            let version_len = version.find('/').unwrap_or(version.len());
+            let version_start = url_start + prefix.len();
+            spans.push(version_start..version_start + version_len);
+        }
+    }
+    spans
+}
+
+/// Returns the versions mentioned by `html_root_url` attributes for `crate_name`.
+pub fn find_versions<'a>(source: &'a str, crate_name: &str) -> Vec<&'a str> {
+    version_spans(source, crate_name)
+        .into_iter()
+        .map(|span| &source[span])
+        .collect()
+}
+
+/// Rewrites the version segment of every `html_root_url` attribute for `crate_name`, leaving
+/// the rest of the source untouched. Returns `None` when there is nothing to rewrite.
+pub fn update_html_root_url(source: &str, crate_name: &str, version: &str) -> Option<String> {
+    let spans = version_spans(source, crate_name);
+    if spans.is_empty() {
+        return None;
+    }
+    let mut output = String::with_capacity(source.len());
+    let mut last = 0;
+    for span in spans {
+        output.push_str(&source[last..span.start]);
+        output.push_str(version);
+        last = span.end;
+    }
+    output.push_str(&source[last..]);
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_version_segment() {
+        let input = "//! Docs\n#![doc(html_root_url = \"https://docs.rs/cargo-bump/1.1.0\")]\n\nfn main() {}\n";
+        let expected = "//! Docs\n#![doc(html_root_url = \"https://docs.rs/cargo-bump/1.2.0\")]\n\nfn main() {}\n";
+        assert_eq!(
+            update_html_root_url(input, "cargo-bump", "1.2.0"),
+            Some(expected.to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_trailing_path_and_spacing() {
+        let input = "#![doc(html_root_url=\"https://docs.rs/cargo_bump/0.1.0/cargo_bump/\")]";
+        let expected =
+            "#![doc(html_root_url=\"https://docs.rs/cargo_bump/0.2.0-beta/cargo_bump/\")]";
+        assert_eq!(
+            update_html_root_url(input, "cargo-bump", "0.2.0-beta"),
+            Some(expected.to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_other_crates() {
+        let input = "#![doc(html_root_url = \"https://docs.rs/serde/1.0.0\")]";
+        assert_eq!(update_html_root_url(input, "cargo-bump", "1.2.0"), None);
+        assert!(find_versions(input, "cargo-bump").is_empty());
+    }
+
+    #[test]
+    fn finds_versions() {
+        let input = "#![doc(html_root_url = \"https://docs.rs/cargo-bump/1.1.0\")]";
+        assert_eq!(find_versions(input, "cargo-bump"), vec!["1.1.0"]);
+    }
+}
@@ -1,42 +1,511 @@
 #![deny(clippy::all)]
 
 extern crate cargo_metadata;
+extern crate chrono;
 extern crate clap;
+extern crate quote;
 extern crate semver;
+#[macro_use]
+extern crate serde_json;
+extern crate syn;
 extern crate toml_edit;
 
+mod api;
+mod build_template;
+mod calver;
+mod changelog;
+mod changeset;
+mod compare;
+mod compat;
 mod config;
+mod conventional;
 mod git;
+mod glob;
+mod html_root_url;
+#[cfg(test)]
+mod scratch;
+mod sync;
+mod verify;
 mod version;
 
-use std::fs::{File, OpenOptions};
+use chrono::Local;
+use std::cmp::Reverse;
+use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::Path;
+use std::process;
 use toml_edit::Document;
 
 use semver::Version;
 
 fn main() {
-    let conf = config::get_config();
-    let raw_data = read_file(&conf.manifest);
-    let use_git = conf.git_tag;
+    match config::get_command() {
+        config::Command::Bump(conf) => bump(conf),
+        config::Command::CheckSync(conf) => check_sync(&conf),
+        config::Command::AddChangeset {
+            workspace,
+            crates,
+            level,
+            summary,
+        } => add_changeset(&workspace, crates, level, summary),
+        config::Command::ApplyChangesets { workspace, commit } => {
+            apply_changesets(&workspace, commit)
+        }
+        config::Command::Suggest { conf, apply } => suggest(conf, apply),
+        config::Command::Verify { workspace, base } => verify(&workspace, &base),
+        config::Command::Compare { old, new, json } => {
+            let comparison = compare::Comparison::new(old, new);
+            if json {
+                println!("{}", comparison.to_json());
+            } else {
+                println!("{}", comparison);
+            }
+        }
+    }
+}
 
-    if use_git {
-        git::git_check();
+fn bump(conf: config::Config) {
+    if let Some(ref release) = conf.git {
+        check_release(&conf, release);
+        if release.commit == git::CommitMode::Skip {
+            return tag_head(&conf, release);
+        }
     }
+    let raw_data = read_file(&conf.manifest);
 
-    let output = update_toml_with_version(&raw_data, conf.version_modifier);
+    let mut version_modifier = conf.version_modifier;
+    if version_modifier.mod_type == config::ModifierType::Auto {
+        let document = raw_data.parse::<Document>().expect("parsed toml");
+        version_modifier.mod_type = infer_mod_type(&read_version(&document));
+    }
+    let output = update_toml_with_version(&raw_data, version_modifier);
     let version = output["package"]["version"].as_str().unwrap();
+    check_manifest_compatibility(&conf.manifest, &raw_data, &output, conf.strict);
 
-    let mut f = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(&conf.manifest)
-        .unwrap();
-    f.write_all(output.to_string().as_bytes()).unwrap();
+    if conf.print_next {
+        println!("{}", version);
+        return;
+    }
+    if conf.snapshot {
+        // Snapshots aren't releases, so only the manifest changes.
+        write_file(&conf.manifest, &output.to_string());
+        return;
+    }
+
+    let changelog_path = conf.manifest.with_file_name("CHANGELOG.md");
+    let existing_changelog = if changelog_path.exists() {
+        read_file(&changelog_path)
+    } else {
+        String::new()
+    };
+    if !conf.changelog
+        && !conf.allow_empty_changelog
+        && changelog::unreleased_is_empty(&existing_changelog)
+    {
+        panic!(
+            "The Unreleased section of {} is empty. Add release notes or pass \
+             --allow-empty-changelog.",
+            changelog_path.display()
+        );
+    }
+    let date = Local::now().format("%Y-%m-%d").to_string();
+
+    write_file(&conf.manifest, &output.to_string());
+    let mut touched = vec![conf.manifest.clone()];
+
+    if let Some(lib_path) = conf.lib_path {
+        let source = read_file(&lib_path);
+        if let Some(updated) =
+            html_root_url::update_html_root_url(&source, &conf.crate_name, version)
+        {
+            write_file(&lib_path, &updated);
+            touched.push(lib_path);
+        }
+    }
+
+    for replacement in &conf.replacements {
+        let source = read_file(&replacement.file);
+        let updated = sync::replace_versions(&source, &replacement.pattern, version);
+        if updated != source {
+            write_file(&replacement.file, &updated);
+            touched.push(replacement.file.clone());
+        }
+    }
+
+    let updated_changelog = if conf.changelog {
+        let template = match conf.changelog_template {
+            Some(ref path) => read_file(path),
+            None => changelog::DEFAULT_TEMPLATE.to_string(),
+        };
+        let previous = git::latest_tag();
+        let commits = git::commits_since(previous.as_deref());
+        let section = changelog::render(&template, version, &date, &commits);
+        Some(changelog::add_release(
+            &existing_changelog,
+            &section,
+            version,
+            &date,
+        ))
+    } else {
+        changelog::rollover(&existing_changelog, version, &date)
+    };
+    if let Some(ref updated) = updated_changelog {
+        write_file(&changelog_path, updated);
+        touched.push(changelog_path);
+    }
+    let notes = changelog::release_notes(
+        updated_changelog.as_ref().unwrap_or(&existing_changelog),
+        version,
+    );
+    if let Some(ref path) = conf.notes_file {
+        write_notes(path, version, notes.as_deref());
+    }
+
+    if let Some(ref release) = conf.git {
+        let released = git::Released {
+            name: conf.crate_name.clone(),
+            version: version.to_string(),
+            previous: Some(
+                read_version(&raw_data.parse::<Document>().expect("parsed toml")).to_string(),
+            ),
+            notes,
+        };
+        let tags = release.record(&[released], &touched);
+        push_release(conf.push.as_deref(), &tags);
+    }
+}
+
+/// Picks the version position to increment from the commits since the last tag, explaining
+/// on stderr which commits drove the decision so `--print-next` output stays clean.
+fn infer_mod_type(current: &Version) -> config::ModifierType {
+    let previous = git::latest_tag();
+    let commits = git::commits_since(previous.as_deref());
+    let (change, reasons) = conventional::infer_change(&commits);
+    let mod_type = version::modifier_for_change(current, change);
+    eprintln!(
+        "{} commit(s) since {}, bumping {:?} for a {:?} release of {}:",
+        commits.len(),
+        previous.as_ref().map_or("the first commit", String::as_str),
+        mod_type,
+        change,
+        current
+    );
+    if reasons.is_empty() {
+        eprintln!("  no Conventional Commits found, defaulting to a fix");
+    }
+    for reason in reasons {
+        eprintln!("  {} {}", reason.short_hash(), reason.commit.subject);
+    }
+    mod_type
+}
+
+/// Tags HEAD with the version its Cargo.toml already has, for bumps committed separately such
+/// as through a merged pull request.
+fn tag_head(conf: &config::Config, release: &git::Release) {
+    let manifest = fs::canonicalize(&conf.manifest).expect("manifest path");
+    let committed = git::show("HEAD", &manifest)
+        .and_then(|manifest| manifest.parse::<Document>().ok())
+        .expect("Cargo.toml is not committed at HEAD.");
+    let version = read_version(&committed).to_string();
+    let notes = git::show("HEAD", &manifest.with_file_name("CHANGELOG.md"))
+        .and_then(|changelog| changelog::release_notes(&changelog, &version));
+    if let Some(ref path) = conf.notes_file {
+        write_notes(path, &version, notes.as_deref());
+    }
+    let released = git::Released {
+        name: conf.crate_name.clone(),
+        version,
+        previous: None,
+        notes,
+    };
+    let tags = release.record(&[released], &[]);
+    push_release(conf.push.as_deref(), &tags);
+}
+
+/// Writes the release notes of `version` for release tooling to pick up, leaving the file
+/// empty when the changelog has none.
+fn write_notes(path: &Path, version: &str, notes: Option<&str>) {
+    if notes.is_none() {
+        eprintln!(
+            "warning: CHANGELOG.md has no notes for {}, writing an empty {}",
+            version,
+            path.display()
+        );
+    }
+    write_file(
+        path,
+        &notes.map_or_else(String::new, |notes| format!("{}\n", notes)),
+    );
+}
+
+/// Refuses to release before anything is written when git, signing or the push remote
+/// aren't ready for it.
+fn check_release(conf: &config::Config, release: &git::Release) {
+    git::git_check(&conf.dirty, &conf.release_branches, release);
+    if let Some(ref signing) = release.sign {
+        if let Some(problem) = git::signing_problem(signing) {
+            panic!("Cannot sign the release: {}.", problem);
+        }
+    }
+    if let Some(ref remote) = conf.push {
+        if !git::remote_exists(remote) {
+            panic!(
+                "There is no git remote named {} to push the release to.",
+                remote
+            );
+        }
+    }
+}
+
+fn push_release(remote: Option<&str>, tags: &[String]) {
+    if let Some(remote) = remote {
+        if let Err(err) = git::push(remote, tags) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
 
-    if use_git {
-        git::git_commit_and_tag(version);
+/// Warns, or refuses with `strict`, when the manifest changed since the last tag in a way the
+/// new version is too small a step for.
+fn check_manifest_compatibility(manifest: &Path, raw_data: &str, output: &Document, strict: bool) {
+    let manifest = fs::canonicalize(manifest).expect("manifest path");
+    let dir = manifest.parent().expect("manifest directory");
+    let tag = match git::latest_tag_in(dir) {
+        Some(tag) => tag,
+        None => return,
+    };
+    let released =
+        match git::show_in(dir, &tag, &manifest).and_then(|old| old.parse::<Document>().ok()) {
+            Some(released) => released,
+            None => return,
+        };
+    let changes = compat::diff(&released, output);
+    let required = match changes.iter().map(|change| change.change).max() {
+        Some(required) => required,
+        None => return,
+    };
+    let current = read_version(&raw_data.parse::<Document>().expect("parsed toml"));
+    let next = read_version(output);
+    let comparison = compare::Comparison::new(current, next);
+    if comparison.difference.allows() >= required {
+        return;
+    }
+    let mut message = format!(
+        "Cargo.toml changed since {} in ways that need a {:?} release, but {} to {} is a {} \
+         change:",
+        tag, required, comparison.old, comparison.new, comparison.difference
+    );
+    for change in &changes {
+        message.push_str(&format!("\n  {:?}: {}", change.change, change.description));
+    }
+    if strict {
+        panic!("{}", message);
+    }
+    eprintln!("warning: {}", message);
+}
+
+fn suggest(conf: config::Config, apply: bool) {
+    let lib_path = conf
+        .lib_path
+        .as_ref()
+        .expect("suggest compares the public API of a library, but there is no lib target.");
+    let lib_path = fs::canonicalize(lib_path).expect("library root");
+    let tag = git::latest_tag()
+        .expect("suggest compares against the last release tag, but there are no tags.");
+    let old = api::collect(&lib_path, |path| git::show(&tag, path))
+        .unwrap_or_else(|err| panic!("Could not read the API at {}: {}", tag, err));
+    let new = api::collect(&lib_path, |path| fs::read_to_string(path).ok())
+        .unwrap_or_else(|err| panic!("Could not read the current API: {}", err));
+    let mut changes = api::diff(&old, &new);
+    changes.sort_by_key(|change| Reverse(change.change));
+
+    let change = changes
+        .first()
+        .map_or(version::Change::Fix, |change| change.change);
+    let current = read_version(
+        &read_file(&conf.manifest)
+            .parse::<Document>()
+            .expect("parsed toml"),
+    );
+    let mod_type = version::modifier_for_change(&current, change);
+    println!(
+        "{} public API change(s) since {}, suggesting {:?} for a {:?} release of {}:",
+        changes.len(),
+        tag,
+        mod_type,
+        change,
+        current
+    );
+    if changes.is_empty() {
+        println!("  the public API is unchanged, defaulting to a fix");
+    }
+    for change in &changes {
+        println!("  {:?}: {}", change.change, change.description);
+    }
+
+    if apply {
+        let version_modifier = config::VersionModifier {
+            mod_type,
+            ..conf.version_modifier
+        };
+        bump(config::Config {
+            version_modifier,
+            ..conf
+        });
+    }
+}
+
+fn check_sync(conf: &config::Config) {
+    let document = read_file(&conf.manifest)
+        .parse::<Document>()
+        .expect("parsed toml");
+    let version = read_version(&document);
+    let mismatches = sync::check_sync(conf, &version);
+    if mismatches.is_empty() {
+        println!("All version references agree with {}.", version);
+    } else {
+        eprintln!("Version references disagree with {}:", version);
+        for mismatch in mismatches {
+            eprintln!("  {}", mismatch);
+        }
+        process::exit(1);
+    }
+}
+
+fn verify(workspace: &config::Workspace, base: &str) {
+    let changed = git::changed_files(base);
+    let members = verify::changed_members(&workspace.members, &changed);
+    if members.is_empty() {
+        println!("No crates changed since {}.", base);
+        return;
+    }
+    let mut failed = false;
+    for member in members {
+        let current = read_version(
+            &read_file(&member.manifest)
+                .parse::<Document>()
+                .expect("parsed toml"),
+        );
+        let released = git::show(base, &member.manifest).map(|manifest| {
+            read_version(
+                &manifest
+                    .parse::<Document>()
+                    .expect("parsed toml at the base"),
+            )
+        });
+        let outcome = verify::Outcome::new(released, current);
+        if outcome.is_ok() {
+            println!("{} {}", member.name, outcome);
+        } else {
+            eprintln!("{} {}", member.name, outcome);
+            failed = true;
+        }
+    }
+    if failed {
+        eprintln!(
+            "Changed crates must bump their version relative to {}.",
+            base
+        );
+        process::exit(1);
+    }
+}
+
+fn add_changeset(
+    workspace: &config::Workspace,
+    crates: Vec<String>,
+    level: changeset::Level,
+    summary: String,
+) {
+    for name in &crates {
+        if workspace.member(name).is_none() {
+            panic!("{} is not a member of this workspace.", name);
+        }
+    }
+    let changeset = changeset::Changeset {
+        releases: crates.into_iter().map(|name| (name, level)).collect(),
+        summary,
+    };
+    let path = changeset::write(&workspace.root, &changeset);
+    println!("Wrote {}", path.display());
+}
+
+fn apply_changesets(workspace: &config::Workspace, commit: Option<git::CommitMessage>) {
+    let pending = changeset::read_all(&workspace.root);
+    if pending.is_empty() {
+        println!("No pending changesets.");
+        return;
+    }
+    let plan = changeset::plan(pending.iter().map(|(_, changeset)| changeset));
+    for name in plan.keys() {
+        if workspace.member(name).is_none() {
+            panic!(
+                "Changesets name {}, which is not a member of this workspace.",
+                name
+            );
+        }
+    }
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    // Every crate is bumped in memory first, so a crate that can't be bumped leaves the
+    // workspace and the changesets untouched.
+    let mut released = Vec::new();
+    let mut writes = Vec::new();
+    for (name, release) in &plan {
+        let member = workspace.member(name).expect("workspace member");
+        let raw_data = read_file(&member.manifest);
+        let output = update_toml_with_version(
+            &raw_data,
+            config::VersionModifier::from_mod_type(release.level.mod_type()),
+        );
+        let version = output["package"]["version"].as_str().unwrap();
+        released.push(git::Released {
+            name: name.clone(),
+            version: version.to_string(),
+            previous: raw_data
+                .parse::<Document>()
+                .ok()
+                .map(|previous| read_version(&previous).to_string()),
+            notes: None,
+        });
+        writes.push((member.manifest.clone(), output.to_string()));
+
+        let changelog_path = member.manifest.with_file_name("CHANGELOG.md");
+        let existing = if changelog_path.exists() {
+            read_file(&changelog_path)
+        } else {
+            String::new()
+        };
+        let section = changeset::changelog_section(version, release);
+        writes.push((
+            changelog_path,
+            changelog::add_release(&existing, &section, version, &date),
+        ));
+    }
+
+    let mut touched = Vec::new();
+    for (path, contents) in writes {
+        write_file(&path, &contents);
+        touched.push(path);
+    }
+    for (krate, release) in released.iter().zip(plan.values()) {
+        println!("{} {} ({})", krate.name, krate.version, release.level);
+    }
+    for (path, _) in pending {
+        let tracked = commit.is_some() && git::is_tracked(&path);
+        fs::remove_file(&path).expect("remove the applied changeset");
+        if tracked {
+            touched.push(path);
+        }
+    }
+
+    if let Some(message) = commit {
+        let release = git::Release {
+            commit: git::CommitMode::New,
+            tag: git::TagMode::Skip,
+            sign: None,
+            message,
+        };
+        release.record(&released, &touched);
     }
 }
 
@@ -47,17 +516,30 @@ fn read_file(file: &Path) -> String {
     raw_data
 }
 
+fn write_file(file: &Path, contents: &str) {
+    let mut f = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(file)
+        .unwrap();
+    f.write_all(contents.as_bytes()).unwrap();
+}
+
+fn read_version(document: &Document) -> Version {
+    document["package"]["version"]
+        .as_str()
+        .expect("toml has version")
+        .parse::<Version>()
+        .expect("version is semver")
+}
+
 fn update_toml_with_version(raw_data: &str, version_modifier: config::VersionModifier) -> Document {
     let mut value = raw_data
         .parse::<toml_edit::Document>()
         .expect("parsed toml");
     let version = {
-        let version_string = value["package"]["version"]
-            .as_str()
-            .expect("toml has version");
-        let mut version = version_string
-            .parse::<Version>()
-            .expect("version is semver");
+        let mut version = read_version(&value);
         version::update_version(&mut version, version_modifier);
         version
     };
@@ -70,6 +552,14 @@ fn update_toml_with_version(raw_data: &str, version_modifier: config::VersionMod
 mod test {
     use super::*;
     use config::{ModifierType, VersionModifier};
+    use scratch::{git, Scratch};
+
+    fn allowing_downgrade(mod_type: ModifierType) -> VersionModifier {
+        VersionModifier {
+            allow_downgrade: true,
+            ..VersionModifier::from_mod_type(mod_type)
+        }
+    }
 
     fn toml_test_wrapper(
         template: &str,
@@ -92,12 +582,7 @@ mod test {
         let input = "[package]
 version = $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.0.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.0.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -141,12 +626,7 @@ version = $VERSION";
         let input = "  [package]
     version = $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -172,12 +652,7 @@ version = $VERSION";
         let input = "  [package]
 version= $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -203,12 +678,7 @@ version= $VERSION";
         let input = "  [package]
 version       = $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -238,12 +708,7 @@ version       = $VERSION";
         let input = "  [package]
 version =$VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -269,12 +734,7 @@ version =$VERSION";
         let input = "  [package]
 version =    $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -300,12 +760,7 @@ version =    $VERSION";
         let input = "  [package]
 version = $VERSION      ";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -337,12 +792,7 @@ version = $VERSION      ";
 [     other]
 a = true";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -368,12 +818,7 @@ a = true";
         let input = "  [  package   ]
     version= $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.1.1",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.1.1", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -439,12 +884,7 @@ a = true";
 [package]
 version = $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.0.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.0.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -470,12 +910,7 @@ version = $VERSION";
         let input = "[package]# end of header
 version = $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.0.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.0.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -502,12 +937,7 @@ version = $VERSION";
 # version = \"2.0.0\"
 version = $VERSION";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.0.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.0.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -542,12 +972,7 @@ d = false
 [a.b]
 c = true";
         let mod_type = "1.0.0".parse().expect("version modifier");
-        toml_test_wrapper(
-            input,
-            VersionModifier::from_mod_type(mod_type),
-            "1.0.0",
-            "1.0.0",
-        );
+        toml_test_wrapper(input, allowing_downgrade(mod_type), "1.0.0", "1.0.0");
         let mod_type = ModifierType::Patch;
         toml_test_wrapper(
             input,
@@ -570,4 +995,94 @@ c = true";
             "2.0.0",
         );
     }
+
+    #[test]
+    fn snapshots_only_write_the_manifest() {
+        let scratch = Scratch::new("snapshot");
+        let dir = &scratch.0;
+        fs::create_dir_all(dir.join("src")).expect("scratch crate");
+        let manifest = dir.join("Cargo.toml");
+        let lib_path = dir.join("src/lib.rs");
+        let changelog_path = dir.join("CHANGELOG.md");
+        let source = "#![doc(html_root_url = \"https://docs.rs/foo/0.1.0\")]\n";
+        let changelog = "# Changelog\n\n## [Unreleased]\n";
+        write_file(
+            &manifest,
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+        );
+        write_file(&lib_path, source);
+        write_file(&changelog_path, changelog);
+        git(dir, &["init", "--quiet"]);
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "--quiet", "-m", "init"]);
+
+        let snapshot = Version::parse("0.1.1-dev.1+ge4cdca2").unwrap();
+        bump(config::Config {
+            version_modifier: VersionModifier::from_mod_type(ModifierType::Replace(snapshot)),
+            manifest: manifest.clone(),
+            crate_name: String::from("foo"),
+            lib_path: Some(lib_path.clone()),
+            readme: None,
+            git: None,
+            print_next: false,
+            snapshot: true,
+            strict: false,
+            changelog: false,
+            changelog_template: None,
+            allow_empty_changelog: false,
+            notes_file: None,
+            dirty: Default::default(),
+            release_branches: Vec::new(),
+            replacements: Vec::new(),
+            push: None,
+        });
+        assert!(read_file(&manifest).contains("version = \"0.1.1-dev.1+ge4cdca2\""));
+        assert_eq!(read_file(&lib_path), source);
+        assert_eq!(read_file(&changelog_path), changelog);
+    }
+
+    #[test]
+    fn applies_changesets_below_unreleased_entries() {
+        let scratch = Scratch::new("apply-changesets");
+        let dir = &scratch.0;
+        let manifest = dir.join("Cargo.toml");
+        let changelog_path = dir.join("CHANGELOG.md");
+        write_file(
+            &manifest,
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+        );
+        write_file(
+            &changelog_path,
+            "# Changelog\n\n## [Unreleased]\n\n- Document the CLI.\n\n## [0.1.0] - 2024-01-01\n\n\
+             - Initial release.\n",
+        );
+        changeset::write(
+            dir,
+            &changeset::Changeset {
+                releases: vec![(String::from("foo"), changeset::Level::Minor)],
+                summary: String::from("Add a --verbose flag."),
+            },
+        );
+
+        let workspace = config::Workspace {
+            root: dir.clone(),
+            members: vec![config::Member {
+                name: String::from("foo"),
+                manifest: manifest.clone(),
+            }],
+        };
+        apply_changesets(&workspace, None);
+        let date = Local::now().format("%Y-%m-%d").to_string();
+        assert!(read_file(&manifest).contains("version = \"0.2.0\""));
+        assert_eq!(
+            read_file(&changelog_path),
+            format!(
+                "# Changelog\n\n## [Unreleased]\n\n## [0.2.0] - {}\n\n- Document the CLI.\n\n\
+                 ### Minor Changes\n\n- Add a --verbose flag.\n\n## [0.1.0] - 2024-01-01\n\n\
+                 - Initial release.\n",
+                date
+            )
+        );
+        assert!(changeset::read_all(dir).is_empty());
+    }
 }
@@ -0,0 +1,244 @@
+use config::Config;
+use git;
+use html_root_url;
+use read_file;
+use semver::Version;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// The placeholder marking where the version appears in a replacement pattern.
+pub const PLACEHOLDER: &str = "{version}";
+
+/// A file mentioning the crate version, configured as `replacements` in
+/// `[package.metadata.bump]`. `pattern` contains `{version}`, such as `foo = "{version}"`.
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    pub file: PathBuf,
+    pub pattern: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub location: String,
+    pub found: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: found {}", self.location, self.found)
+    }
+}
+
+/// Collects every place mentioning the crate version that disagrees with `version`.
+pub fn check_sync(conf: &Config, version: &Version) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+
+    if let Some(ref readme) = conf.readme {
+        let source = read_file(readme);
+        for (line, found) in readme_versions(&source, &conf.crate_name) {
+            if !requirement_agrees(&found, version) {
+                mismatches.push(Mismatch {
+                    location: format!("{}:{}", readme.display(), line),
+                    found,
+                });
+            }
+        }
+    }
+
+    if let Some(ref lib_path) = conf.lib_path {
+        let source = read_file(lib_path);
+        for found in html_root_url::find_versions(&source, &conf.crate_name) {
+            if found != version.to_string() {
+                mismatches.push(Mismatch {
+                    location: format!("{} html_root_url", lib_path.display()),
+                    found: found.to_string(),
+                });
+            }
+        }
+    }
+
+    for replacement in &conf.replacements {
+        let source = match fs::read_to_string(&replacement.file) {
+            Ok(source) => source,
+            Err(_) => {
+                mismatches.push(Mismatch {
+                    location: replacement.file.display().to_string(),
+                    found: String::from("no such file"),
+                });
+                continue;
+            }
+        };
+        let found = pattern_versions(&source, &replacement.pattern);
+        if found.is_empty() {
+            mismatches.push(Mismatch {
+                location: replacement.file.display().to_string(),
+                found: format!("nothing matching `{}`", replacement.pattern),
+            });
+        }
+        for (line, found) in found {
+            if found != version.to_string() {
+                mismatches.push(Mismatch {
+                    location: format!("{}:{}", replacement.file.display(), line),
+                    found,
+                });
+            }
+        }
+    }
+
+    if let Some(tag) = git::latest_tag() {
+        let agrees = Version::parse(tag.trim_start_matches('v'))
+            .map(|tagged| tagged == *version)
+            .unwrap_or(false);
+        if !agrees {
+            mismatches.push(Mismatch {
+                location: String::from("latest git tag"),
+                found: tag,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/// Finds the versions where `pattern` matches in `source`, returning the 1-based line number,
+/// the byte range and the version of each match.
+fn pattern_matches(source: &str, pattern: &str) -> Vec<(usize, usize, usize)> {
+    let (prefix, suffix) = match pattern.find(PLACEHOLDER) {
+        Some(at) => (&pattern[..at], &pattern[at + PLACEHOLDER.len()..]),
+        None => return Vec::new(),
+    };
+    let is_version_char = |c: char| c.is_ascii_alphanumeric() || ".+-".contains(c);
+    let mut matches = Vec::new();
+    let mut offset = 0;
+    while let Some(at) = source[offset..].find(prefix) {
+        let start = offset + at + prefix.len();
+        let run = source[start..]
+            .find(|c: char| !is_version_char(c))
+            .map_or(source.len(), |length| start + length);
+        // The shortest version followed by the suffix, as suffixes like `-linux` also
+        // consist of version characters.
+        let end = (start + 1..=run).find(|&end| source[end..].starts_with(suffix));
+        match end {
+            Some(end) => {
+                let line = source[..start].matches('\n').count() + 1;
+                matches.push((line, start, end));
+                offset = end;
+            }
+            None => offset = start.max(offset + at + 1),
+        }
+    }
+    matches
+}
+
+/// The 1-based line number and version of every match of `pattern` in `source`.
+pub fn pattern_versions(source: &str, pattern: &str) -> Vec<(usize, String)> {
+    pattern_matches(source, pattern)
+        .into_iter()
+        .map(|(line, start, end)| (line, source[start..end].to_string()))
+        .collect()
+}
+
+/// Rewrites every version matching `pattern` in `source` to `version`.
+pub fn replace_versions(source: &str, pattern: &str, version: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (_, start, end) in pattern_matches(source, pattern) {
+        output.push_str(&source[copied..start]);
+        output.push_str(version);
+        copied = end;
+    }
+    output.push_str(&source[copied..]);
+    output
+}
+
+/// Finds dependency snippets such as `crate = "1.2"` or `crate = { version = "1.2" }`,
+/// returning the 1-based line number and the version requirement.
+fn readme_versions(source: &str, crate_name: &str) -> Vec<(usize, String)> {
+    source
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let rest = line.trim().strip_prefix(crate_name)?;
+            let value = rest.trim_start().strip_prefix('=')?.trim_start();
+            let value = if value.starts_with('{') {
+                let after_key = &value[value.find("version")? + "version".len()..];
+                after_key.trim_start().strip_prefix('=')?.trim_start()
+            } else {
+                value
+            };
+            let value = value.strip_prefix('"')?;
+            let end = value.find('"')?;
+            Some((index + 1, value[..end].to_string()))
+        })
+        .collect()
+}
+
+/// A requirement agrees with a version when every component it spells out matches, so
+/// `1.2` agrees with `1.2.3` but `1.1` does not.
+fn requirement_agrees(requirement: &str, version: &Version) -> bool {
+    let requirement = requirement.trim_start_matches(&['^', '=', '~'][..]);
+    if requirement.contains('-') {
+        return Version::parse(requirement)
+            .map(|required| required == *version)
+            .unwrap_or(false);
+    }
+    let components = [version.major, version.minor, version.patch];
+    for (part, component) in requirement.split('.').zip(components.iter()) {
+        match part.parse::<u64>() {
+            Ok(number) if number == *component => continue,
+            Ok(_) => return false,
+            Err(_) => return part == "*" || part == "x",
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_readme_snippets() {
+        let readme = "# foo\n\n```toml\n[dependencies]\nfoo = \"1.2\"\nfoo-bar = \"3\"\nfoo = { version = \"1.1.0\", features = [\"x\"] }\n```\n";
+        assert_eq!(
+            readme_versions(readme, "foo"),
+            vec![(5, String::from("1.2")), (7, String::from("1.1.0"))]
+        );
+    }
+
+    #[test]
+    fn finds_and_replaces_pattern_versions() {
+        let source =
+            "curl -L https://x/foo-v1.2.0-linux.tar.gz\nfoo = \"1.2.0\"\nfoo = \"1.1.0-rc.1\"\n";
+        assert_eq!(
+            pattern_versions(source, "foo = \"{version}\""),
+            vec![(2, String::from("1.2.0")), (3, String::from("1.1.0-rc.1"))]
+        );
+        assert_eq!(
+            pattern_versions(source, "foo-v{version}-linux"),
+            vec![(1, String::from("1.2.0"))]
+        );
+        assert!(pattern_versions(source, "bar = \"{version}\"").is_empty());
+        assert_eq!(
+            replace_versions(source, "foo = \"{version}\"", "1.3.0"),
+            "curl -L https://x/foo-v1.2.0-linux.tar.gz\nfoo = \"1.3.0\"\nfoo = \"1.3.0\"\n"
+        );
+        assert_eq!(
+            replace_versions("v{version}", "version: {version}", "1.0.0"),
+            "v{version}"
+        );
+    }
+
+    #[test]
+    fn requirements_agree_by_component() {
+        let version = Version::parse("1.2.3").unwrap();
+        assert!(requirement_agrees("1", &version));
+        assert!(requirement_agrees("1.2", &version));
+        assert!(requirement_agrees("^1.2.3", &version));
+        assert!(requirement_agrees("1.*", &version));
+        assert!(!requirement_agrees("1.1", &version));
+        assert!(!requirement_agrees("=1.2.2", &version));
+        assert!(!requirement_agrees("1.2.3-beta", &version));
+    }
+}
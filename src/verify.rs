@@ -0,0 +1,137 @@
+use config::Member;
+use semver::Version;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What happened to a changed crate's version relative to the base ref.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Bumped {
+        from: Version,
+        to: Version,
+    },
+    NotBumped(Version),
+    WentBackwards {
+        from: Version,
+        to: Version,
+    },
+    /// The crate doesn't exist at the base ref.
+    New(Version),
+}
+
+impl Outcome {
+    pub fn new(base: Option<Version>, current: Version) -> Outcome {
+        match base {
+            None => Outcome::New(current),
+            Some(base) => {
+                if current > base {
+                    Outcome::Bumped {
+                        from: base,
+                        to: current,
+                    }
+                } else if current == base {
+                    Outcome::NotBumped(current)
+                } else {
+                    Outcome::WentBackwards {
+                        from: base,
+                        to: current,
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        match *self {
+            Outcome::Bumped { .. } | Outcome::New(_) => true,
+            Outcome::NotBumped(_) | Outcome::WentBackwards { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Outcome::Bumped { ref from, ref to } => write!(f, "bumped from {} to {}", from, to),
+            Outcome::NotBumped(ref version) => write!(f, "changed but still at {}", version),
+            Outcome::WentBackwards { ref from, ref to } => {
+                write!(f, "went backwards from {} to {}", from, to)
+            }
+            Outcome::New(ref version) => write!(f, "new at {}", version),
+        }
+    }
+}
+
+/// The members owning any of the `changed` paths. A path belongs to the member with the
+/// deepest directory containing it, so a crate at the workspace root doesn't claim the files
+/// of the crates nested inside it.
+pub fn changed_members<'a>(members: &'a [Member], changed: &[PathBuf]) -> Vec<&'a Member> {
+    let directory = |member: &Member| -> PathBuf {
+        member
+            .manifest
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf)
+    };
+    let mut owners: Vec<&Member> = Vec::new();
+    for path in changed {
+        let owner = members
+            .iter()
+            .filter(|member| path.starts_with(directory(member)))
+            .max_by_key(|member| directory(member).components().count());
+        if let Some(owner) = owner {
+            if !owners.iter().any(|known| known.name == owner.name) {
+                owners.push(owner);
+            }
+        }
+    }
+    owners.sort_by(|a, b| a.name.cmp(&b.name));
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, manifest: &str) -> Member {
+        Member {
+            name: name.to_string(),
+            manifest: PathBuf::from(manifest),
+        }
+    }
+
+    #[test]
+    fn finds_owning_members() {
+        let members = vec![
+            member("root", "/ws/Cargo.toml"),
+            member("core", "/ws/crates/core/Cargo.toml"),
+            member("cli", "/ws/crates/cli/Cargo.toml"),
+        ];
+        let changed = vec![
+            PathBuf::from("/ws/crates/core/src/lib.rs"),
+            PathBuf::from("/ws/crates/core/Cargo.toml"),
+            PathBuf::from("/ws/README.md"),
+        ];
+        let names: Vec<&str> = changed_members(&members, &changed)
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["core", "root"]);
+        assert!(changed_members(&members[1..], &changed[2..]).is_empty());
+    }
+
+    #[test]
+    fn judges_versions() {
+        let version = |v| Version::parse(v).unwrap();
+        assert!(Outcome::new(Some(version("1.0.0")), version("1.0.1")).is_ok());
+        assert!(Outcome::new(None, version("0.1.0")).is_ok());
+        let outcome = Outcome::new(Some(version("1.0.0")), version("1.0.0"));
+        assert!(!outcome.is_ok());
+        assert_eq!(outcome.to_string(), "changed but still at 1.0.0");
+        let outcome = Outcome::new(Some(version("1.0.0")), version("1.0.0-rc.1"));
+        assert!(!outcome.is_ok());
+        assert_eq!(
+            outcome.to_string(),
+            "went backwards from 1.0.0 to 1.0.0-rc.1"
+        );
+    }
+}
@@ -1,12 +1,32 @@
-use config::{ModifierType, VersionModifier};
-use semver::Version;
+use chrono::{Local, NaiveDate};
+use config::{ModifierType, Position, VersionModifier};
+use semver::{Identifier, Version};
 
 pub fn update_version(old: &mut Version, by: VersionModifier) {
-    match by.mod_type {
+    update_version_on(old, by, Local::now().naive_local().date())
+}
+
+/// Like `update_version`, with `today` standing in for the clock used by calendar versioning.
+pub fn update_version_on(old: &mut Version, by: VersionModifier, today: NaiveDate) {
+    let previous = old.clone();
+    let mod_type = match by.mod_type {
+        ModifierType::Breaking => modifier_for_change(old, Change::Breaking),
+        ModifierType::Feature => modifier_for_change(old, Change::Feature),
+        ModifierType::Fix => modifier_for_change(old, Change::Fix),
+        mod_type => mod_type,
+    };
+    match mod_type {
         ModifierType::Replace(v) => {
             *old = v;
         }
         ModifierType::Major => {
+            refuse_major_before_1(old);
+            old.increment_major();
+        }
+        ModifierType::Graduate => {
+            if old.major > 0 {
+                panic!("{} is already 1.0.0 or later, use major instead.", old);
+            }
             old.increment_major();
         }
         ModifierType::Minor => {
@@ -15,12 +35,398 @@ pub fn update_version(old: &mut Version, by: VersionModifier) {
         ModifierType::Patch => {
             old.increment_patch();
         }
+        ModifierType::CalVer(format) => {
+            *old = format.next(old, today);
+        }
+        ModifierType::Step {
+            position,
+            amount,
+            keep_pre,
+            keep_build,
+        } => {
+            let overflow = || panic!("{} is too large to add {} to.", old, amount);
+            match position {
+                Position::Major => {
+                    refuse_major_before_1(old);
+                    old.major = old.major.checked_add(amount).unwrap_or_else(overflow);
+                    old.minor = 0;
+                    old.patch = 0;
+                }
+                Position::Minor => {
+                    old.minor = old.minor.checked_add(amount).unwrap_or_else(overflow);
+                    old.patch = 0;
+                }
+                Position::Patch => {
+                    old.patch = old.patch.checked_add(amount).unwrap_or_else(overflow);
+                }
+            }
+            if !keep_pre {
+                old.pre.clear();
+            }
+            if !keep_build {
+                old.build.clear();
+            }
+        }
+        ModifierType::Set {
+            major,
+            minor,
+            patch,
+        } => {
+            old.major = major.unwrap_or(old.major);
+            old.minor = minor.unwrap_or(old.minor);
+            old.patch = patch.unwrap_or(old.patch);
+            old.pre.clear();
+            old.build.clear();
+        }
+        ModifierType::Breaking | ModifierType::Feature | ModifierType::Fix => {
+            unreachable!("semantic modifiers are resolved above")
+        }
+        ModifierType::Auto => unreachable!("auto is resolved from the commits before bumping"),
     }
 
     if let Some(pre) = by.pre_release {
         old.pre = pre;
     }
+    if let Some(tags) = by.pre_release_tags {
+        let next = tags
+            .iter()
+            .filter(|tag| {
+                (tag.major, tag.minor, tag.patch) == (old.major, old.minor, old.patch)
+                    && tag.pre.len() == old.pre.len() + 1
+                    && tag.pre.starts_with(&old.pre)
+            })
+            .filter_map(|tag| match tag.pre.last() {
+                Some(&Identifier::Numeric(number)) => Some(number + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1);
+        old.pre.push(Identifier::Numeric(next));
+    }
     if let Some(build) = by.build_metadata {
         old.build = build;
     }
+
+    if !by.allow_downgrade && *old <= previous {
+        panic!(
+            "Refusing to change the version from {} to {}, which does not come after it. \
+             Pass --allow-downgrade to do it anyway.",
+            previous, old
+        );
+    }
+}
+
+fn refuse_major_before_1(version: &Version) {
+    if version.major == 0 {
+        panic!(
+            "{} is before 1.0.0, where major would release 1.0.0. Use breaking for a \
+             breaking change, or major --graduate to release 1.0.0.",
+            version
+        );
+    }
+}
+
+/// Builds a development snapshot version such as `1.4.1-dev.17+g3f2a9c1` for a build `commits`
+/// commits after `release`. It is a pre-release of the next patch, or extends the pre-release
+/// of `release`, so that it sorts after `release` and before whatever is released next.
+pub fn snapshot_version(release: &Version, commits: u64, short_sha: &str) -> Version {
+    let mut snapshot = release.clone();
+    if !snapshot.is_prerelease() {
+        snapshot.increment_patch();
+    }
+    snapshot
+        .pre
+        .push(Identifier::AlphaNumeric(String::from("dev")));
+    snapshot.pre.push(Identifier::Numeric(commits));
+    snapshot.build = vec![Identifier::AlphaNumeric(format!("g{}", short_sha))];
+    snapshot
+}
+
+/// The kind of change a release contains, independent of the current version.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Change {
+    Fix,
+    Feature,
+    Breaking,
+}
+
+/// Picks the component to increment for `change` following Cargo's compatibility rules, where
+/// the left-most non-zero component is the breaking one: a breaking change to `0.y.z` bumps
+/// the minor version and one to `0.0.z` bumps the patch.
+pub fn modifier_for_change(current: &Version, change: Change) -> ModifierType {
+    match (current.major, current.minor, change) {
+        (0, 0, _) => ModifierType::Patch,
+        (0, _, Change::Breaking) => ModifierType::Minor,
+        (0, _, _) => ModifierType::Patch,
+        (_, _, Change::Breaking) => ModifierType::Major,
+        (_, _, Change::Feature) => ModifierType::Minor,
+        (_, _, Change::Fix) => ModifierType::Patch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn updated(current: &str, by: VersionModifier) -> String {
+        let mut version = Version::parse(current).unwrap();
+        update_version(&mut version, by);
+        version.to_string()
+    }
+
+    #[test]
+    #[should_panic(expected = "from 1.1.0 to 1.0.0")]
+    fn refuses_downgrade() {
+        let mod_type = ModifierType::Replace(Version::parse("1.0.0").unwrap());
+        updated("1.1.0", VersionModifier::from_mod_type(mod_type));
+    }
+
+    #[test]
+    #[should_panic(expected = "from 1.0.0 to 1.0.0")]
+    fn refuses_identical_version() {
+        let mod_type = ModifierType::Replace(Version::parse("1.0.0").unwrap());
+        updated("1.0.0", VersionModifier::from_mod_type(mod_type));
+    }
+
+    #[test]
+    #[should_panic(expected = "from 1.0.0 to 1.0.0-rc")]
+    fn refuses_pre_release_of_current_version() {
+        let mod_type = ModifierType::Replace(Version::parse("1.0.0").unwrap());
+        updated("1.0.0", VersionModifier::new(mod_type, Some("rc"), None));
+    }
+
+    #[test]
+    fn allows_downgrade_when_asked() {
+        let mod_type = ModifierType::Replace(Version::parse("1.0.0").unwrap());
+        let by = VersionModifier {
+            allow_downgrade: true,
+            ..VersionModifier::from_mod_type(mod_type)
+        };
+        assert_eq!(updated("1.1.0", by), "1.0.0");
+        let mod_type = ModifierType::Replace(Version::parse("1.0.0").unwrap());
+        assert_eq!(
+            updated("1.0.0-rc.1", VersionModifier::from_mod_type(mod_type)),
+            "1.0.0"
+        );
+    }
+
+    #[test]
+    fn semantic_keywords_follow_cargo_rules() {
+        let semantic = VersionModifier::from_mod_type;
+        assert_eq!(updated("1.2.3", semantic(ModifierType::Breaking)), "2.0.0");
+        assert_eq!(updated("1.2.3", semantic(ModifierType::Feature)), "1.3.0");
+        assert_eq!(updated("1.2.3", semantic(ModifierType::Fix)), "1.2.4");
+        assert_eq!(updated("0.2.3", semantic(ModifierType::Breaking)), "0.3.0");
+        assert_eq!(updated("0.2.3", semantic(ModifierType::Feature)), "0.2.4");
+        assert_eq!(updated("0.0.3", semantic(ModifierType::Breaking)), "0.0.4");
+    }
+
+    #[test]
+    fn calver_uses_injected_date() {
+        let today = NaiveDate::from_ymd_opt(2026, 10, 3).unwrap();
+        let calver = || VersionModifier::from_mod_type(ModifierType::CalVer(Default::default()));
+        let mut version = Version::parse("2026.10.2").unwrap();
+        update_version_on(&mut version, calver(), today);
+        assert_eq!(version.to_string(), "2026.10.3");
+        let mut version = Version::parse("2026.9.2").unwrap();
+        update_version_on(
+            &mut version,
+            VersionModifier::new(calver().mod_type, Some("rc"), None),
+            today,
+        );
+        assert_eq!(version.to_string(), "2026.10.0-rc");
+    }
+
+    #[test]
+    fn graduates_to_one_point_oh() {
+        let by = VersionModifier::from_mod_type(ModifierType::Graduate);
+        assert_eq!(updated("0.9.3", by), "1.0.0");
+    }
+
+    #[test]
+    #[should_panic(expected = "major --graduate")]
+    fn refuses_major_before_one_point_oh() {
+        updated("0.9.3", VersionModifier::from_mod_type(ModifierType::Major));
+    }
+
+    #[test]
+    #[should_panic(expected = "already 1.0.0 or later")]
+    fn refuses_graduate_after_one_point_oh() {
+        updated(
+            "1.0.0",
+            VersionModifier::from_mod_type(ModifierType::Graduate),
+        );
+    }
+
+    fn step(position: Position, amount: u64, keep_pre: bool, keep_build: bool) -> ModifierType {
+        ModifierType::Step {
+            position,
+            amount,
+            keep_pre,
+            keep_build,
+        }
+    }
+
+    #[test]
+    fn steps_interact_with_pre_and_build() {
+        use config::Position::{Major, Minor, Patch};
+        // Each step is applied to 1.2.3, 1.2.3-rc.1, 1.2.3+b.7 and 1.2.3-rc.1+b.7.
+        let cases = vec![
+            (
+                step(Major, 1, false, false),
+                ["2.0.0", "2.0.0", "2.0.0", "2.0.0"],
+            ),
+            (
+                step(Minor, 2, false, false),
+                ["1.4.0", "1.4.0", "1.4.0", "1.4.0"],
+            ),
+            (
+                step(Patch, 3, false, false),
+                ["1.2.6", "1.2.6", "1.2.6", "1.2.6"],
+            ),
+            (
+                step(Major, 1, true, false),
+                ["2.0.0", "2.0.0-rc.1", "2.0.0", "2.0.0-rc.1"],
+            ),
+            (
+                step(Minor, 1, false, true),
+                ["1.3.0", "1.3.0", "1.3.0+b.7", "1.3.0+b.7"],
+            ),
+            (
+                step(Patch, 2, true, true),
+                ["1.2.5", "1.2.5-rc.1", "1.2.5+b.7", "1.2.5-rc.1+b.7"],
+            ),
+        ];
+        let starts = ["1.2.3", "1.2.3-rc.1", "1.2.3+b.7", "1.2.3-rc.1+b.7"];
+        for (mod_type, expected) in cases {
+            for (start, expected) in starts.iter().zip(expected.iter()) {
+                let by = VersionModifier::from_mod_type(mod_type.clone());
+                assert_eq!(&updated(start, by), expected, "{:?} on {}", mod_type, start);
+            }
+        }
+    }
+
+    #[test]
+    fn explicit_pre_and_build_win_over_kept_ones() {
+        use config::Position::Minor;
+        let by = |pre, build| VersionModifier::new(step(Minor, 2, true, true), pre, build);
+        assert_eq!(
+            updated("1.2.3-rc.1+b.7", by(Some("beta"), None)),
+            "1.4.0-beta+b.7"
+        );
+        assert_eq!(
+            updated("1.2.3-rc.1+b.7", by(None, Some("9"))),
+            "1.4.0-rc.1+9"
+        );
+        assert_eq!(
+            updated("1.2.3-rc.1+b.7", by(Some("beta"), Some("9"))),
+            "1.4.0-beta+9"
+        );
+    }
+
+    #[test]
+    fn sets_positions() {
+        let set = |major, minor, patch| ModifierType::Set {
+            major,
+            minor,
+            patch,
+        };
+        assert_eq!(
+            updated(
+                "1.2.3",
+                VersionModifier::from_mod_type(set(None, Some(7), None))
+            ),
+            "1.7.3"
+        );
+        assert_eq!(
+            updated(
+                "1.2.3-rc.1+b.7",
+                VersionModifier::from_mod_type(set(Some(2), None, Some(0)))
+            ),
+            "2.2.0"
+        );
+        assert_eq!(
+            updated(
+                "1.2.3-rc.1",
+                VersionModifier::new(set(None, None, Some(3)), Some("rc.2"), Some("b"))
+            ),
+            "1.2.3-rc.2+b"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "from 1.2.3 to 1.2.0")]
+    fn setting_lower_position_is_a_downgrade() {
+        let mod_type = ModifierType::Set {
+            major: None,
+            minor: None,
+            patch: Some(0),
+        };
+        updated("1.2.3", VersionModifier::from_mod_type(mod_type));
+    }
+
+    #[test]
+    #[should_panic(expected = "before 1.0.0")]
+    fn refuses_major_step_before_1() {
+        let mod_type = step(Position::Major, 2, true, false);
+        updated("0.9.3", VersionModifier::from_mod_type(mod_type));
+    }
+
+    #[test]
+    fn numbers_pre_release_after_existing_tags() {
+        let tags = [
+            "2.1.0-feat-x.1",
+            "2.1.0-feat-x.2",
+            "2.0.0-feat-x.7",
+            "2.1.0-feat-y.5",
+        ];
+        let by = || VersionModifier {
+            pre_release_tags: Some(
+                tags.iter()
+                    .map(|tag| Version::parse(tag).unwrap())
+                    .collect(),
+            ),
+            ..VersionModifier::new(ModifierType::Minor, Some("feat-x"), None)
+        };
+        assert_eq!(updated("2.0.0", by()), "2.1.0-feat-x.3");
+        assert_eq!(updated("3.0.0", by()), "3.1.0-feat-x.1");
+    }
+
+    #[test]
+    fn snapshot_follows_next_patch() {
+        let release = Version::parse("1.4.0").unwrap();
+        let snapshot = snapshot_version(&release, 17, "3f2a9c1");
+        assert_eq!(snapshot.to_string(), "1.4.1-dev.17+g3f2a9c1");
+        assert!(snapshot > release);
+        assert!(snapshot < Version::parse("1.4.1").unwrap());
+        assert!(snapshot_version(&release, 9, "a") < snapshot_version(&release, 10, "a"));
+    }
+
+    #[test]
+    fn snapshot_of_pre_release_keeps_its_core() {
+        let release = Version::parse("2.0.0-rc.1").unwrap();
+        let snapshot = snapshot_version(&release, 3, "3f2a9c1");
+        assert_eq!(snapshot.to_string(), "2.0.0-rc.1.dev.3+g3f2a9c1");
+        assert!(snapshot > release);
+    }
+
+    fn modifier(current: &str, change: Change) -> ModifierType {
+        modifier_for_change(&Version::parse(current).unwrap(), change)
+    }
+
+    #[test]
+    fn changes_after_one_point_oh() {
+        assert_eq!(modifier("1.2.3", Change::Breaking), ModifierType::Major);
+        assert_eq!(modifier("1.2.3", Change::Feature), ModifierType::Minor);
+        assert_eq!(modifier("1.2.3", Change::Fix), ModifierType::Patch);
+    }
+
+    #[test]
+    fn changes_before_one_point_oh() {
+        assert_eq!(modifier("0.2.3", Change::Breaking), ModifierType::Minor);
+        assert_eq!(modifier("0.2.3", Change::Feature), ModifierType::Patch);
+        assert_eq!(modifier("0.2.3", Change::Fix), ModifierType::Patch);
+        assert_eq!(modifier("0.0.3", Change::Breaking), ModifierType::Patch);
+        assert_eq!(modifier("0.0.3", Change::Feature), ModifierType::Patch);
+    }
 }